@@ -0,0 +1,226 @@
+use crate::buffer::Buffer;
+
+/// Length of the APE tag footer, and of the header when there is one.
+pub(crate) const FOOTER_LENGTH: usize = 32;
+
+const FLAG_HAS_HEADER: u32 = 1 << 31;
+const FLAG_IS_HEADER: u32 = 1 << 29;
+
+/// Value of an APE tag item.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApeValue {
+    /// UTF-8 text, several values separated by zero bytes.
+    Text(String),
+    Binary(Vec<u8>),
+    /// Link to external data, such as a URL.
+    Locator(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApeItem {
+    pub key: String,
+    pub value: ApeValue,
+    pub read_only: bool,
+}
+
+/// APEv1 or APEv2 tag at the end of the file, found by its footer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApeTag {
+    /// 1000 for APEv1, 2000 for APEv2.
+    pub version: u32,
+    /// Length of the whole tag in bytes, including the header and the footer.
+    pub length: usize,
+    pub items: Vec<ApeItem>,
+}
+
+fn read_u32_le(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+impl ApeTag {
+    /// Reads the tag ending at the end of `buffer`.
+    pub fn create_from_buffer(buffer: &Buffer) -> Option<Self> {
+        Self::create_from_bytes(&buffer.data)
+    }
+
+    /// Length of the whole tag from an APEv2 header at the start of `data`, which lets a stream
+    /// skip a tag before reaching its footer.
+    pub fn length_from_header(data: &[u8]) -> Option<usize> {
+        let header = data.get(..FOOTER_LENGTH)?;
+
+        if !header.starts_with(b"APETAGEX") {
+            return None;
+        }
+
+        let version = read_u32_le(header, 8)?;
+        let size = read_u32_le(header, 12)? as usize;
+        let flags = read_u32_le(header, 20)?;
+
+        if version < 2000 || flags & FLAG_IS_HEADER == 0 || size < FOOTER_LENGTH {
+            return None;
+        }
+
+        Some(size + FOOTER_LENGTH)
+    }
+
+    /// Reads the tag ending at the end of `data`.
+    pub fn create_from_bytes(data: &[u8]) -> Option<Self> {
+        let footer = data.get(data.len().checked_sub(FOOTER_LENGTH)?..)?;
+
+        if !footer.starts_with(b"APETAGEX") {
+            return None;
+        }
+
+        let version = read_u32_le(footer, 8)?;
+        // The size counts the items and the footer, but not the header.
+        let size = read_u32_le(footer, 12)? as usize;
+        let item_count = read_u32_le(footer, 16)?;
+        let flags = read_u32_le(footer, 20)?;
+
+        if flags & FLAG_IS_HEADER != 0 || size < FOOTER_LENGTH {
+            return None;
+        }
+
+        // APEv1 has no flags and never a header.
+        let has_header = version >= 2000 && flags & FLAG_HAS_HEADER != 0;
+        let length = size + if has_header { FOOTER_LENGTH } else { 0 };
+
+        if length > data.len() {
+            return None;
+        }
+
+        let items = &data[data.len() - size..data.len() - FOOTER_LENGTH];
+
+        let mut tag = Self {
+            version,
+            length,
+            items: Vec::new(),
+        };
+
+        let mut pos = 0;
+
+        for _ in 0..item_count {
+            let (Some(item_size), Some(item_flags)) =
+                (read_u32_le(items, pos), read_u32_le(items, pos + 4))
+            else {
+                break;
+            };
+
+            // APEv1 items are always text.
+            let item_flags = if version >= 2000 { item_flags } else { 0 };
+
+            let key_start = pos + 8;
+            let Some(key_length) = items
+                .get(key_start..)
+                .and_then(|rest| rest.iter().position(|&byte| byte == 0))
+            else {
+                break;
+            };
+
+            let value_start = key_start + key_length + 1;
+            let Some(value) = items.get(value_start..value_start + item_size as usize) else {
+                break;
+            };
+
+            pos = value_start + item_size as usize;
+
+            let value = match item_flags >> 1 & 3 {
+                1 => ApeValue::Binary(value.to_vec()),
+                2 => ApeValue::Locator(String::from_utf8_lossy(value).into_owned()),
+                _ => ApeValue::Text(String::from_utf8_lossy(value).into_owned()),
+            };
+
+            tag.items.push(ApeItem {
+                key: String::from_utf8_lossy(&items[key_start..key_start + key_length])
+                    .into_owned(),
+                value,
+                read_only: item_flags & 1 != 0,
+            });
+        }
+
+        Some(tag)
+    }
+
+    /// Item with the given key, which APE compares ignoring case.
+    pub fn get(&self, key: &str) -> Option<&ApeItem> {
+        self.items
+            .iter()
+            .find(|item| item.key.eq_ignore_ascii_case(key))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn item(key: &str, flags: u32, value: &[u8]) -> Vec<u8> {
+        let mut data = (value.len() as u32).to_le_bytes().to_vec();
+
+        data.extend_from_slice(&flags.to_le_bytes());
+        data.extend_from_slice(key.as_bytes());
+        data.push(0);
+        data.extend_from_slice(value);
+        data
+    }
+
+    fn header(version: u32, size: usize, items: u32, flags: u32) -> Vec<u8> {
+        let mut data = b"APETAGEX".to_vec();
+
+        for field in [version, size as u32, items, flags] {
+            data.extend_from_slice(&field.to_le_bytes());
+        }
+
+        data.extend_from_slice(&[0; 8]);
+        data
+    }
+
+    #[test]
+    fn test_apev2_tag() {
+        let mut items = item("Title", 0, b"Title");
+        items.extend(item("Cover Art (Front)", 2, b"cover.jpg\0\xff\xd8"));
+        items.extend(item("Related", 4 | 1, b"http://example.com"));
+
+        let size = items.len() + FOOTER_LENGTH;
+        let mut data = b"audio".to_vec();
+        data.extend(header(2000, size, 3, FLAG_HAS_HEADER | FLAG_IS_HEADER));
+        data.extend(items);
+        data.extend(header(2000, size, 3, FLAG_HAS_HEADER));
+
+        let tag = ApeTag::create_from_bytes(&data).unwrap();
+
+        assert_eq!(ApeTag::length_from_header(&data[5..]), Some(tag.length));
+        assert_eq!(ApeTag::length_from_header(&data[data.len() - 32..]), None);
+        assert_eq!(tag.length, data.len() - 5);
+        assert_eq!(tag.items.len(), 3);
+        assert_eq!(
+            tag.get("TITLE").unwrap().value,
+            ApeValue::Text("Title".to_string())
+        );
+        assert_eq!(
+            tag.items[1].value,
+            ApeValue::Binary(b"cover.jpg\0\xff\xd8".to_vec())
+        );
+        assert!(tag.items[2].read_only);
+        assert!(matches!(tag.items[2].value, ApeValue::Locator(_)));
+    }
+
+    #[test]
+    fn test_apev1_tag() {
+        let items = item("Artist", 0, b"Artist");
+        let mut data = items.clone();
+        data.extend(header(1000, items.len() + FOOTER_LENGTH, 1, 0));
+
+        let tag = ApeTag::create_from_bytes(&data).unwrap();
+
+        assert_eq!(tag.version, 1000);
+        assert_eq!(tag.length, data.len());
+        assert_eq!(
+            tag.get("artist").unwrap().value,
+            ApeValue::Text("Artist".to_string())
+        );
+
+        assert_eq!(ApeTag::create_from_bytes(&data[..data.len() - 1]), None);
+    }
+}
@@ -1,30 +1,69 @@
-use std::{fs, io::Read};
+use std::{
+    borrow::Cow,
+    fs,
+    io::{self, Read},
+};
 
-use crate::error::ErrorType;
+use crate::{error::ErrorType, header::Header};
 
-pub struct Buffer {
-    pub data: Vec<u8>,
-    pub pos: usize,
-    pub total_bits: usize,
+/// Bit reader over bytes that are either owned or borrowed, such as a memory mapped file or an
+/// `include_bytes!` asset, which are then decoded without being copied.
+pub struct Buffer<'a> {
+    pub(crate) data: Cow<'a, [u8]>,
+    pub(crate) pos: usize,
+    pub(crate) total_bits: usize,
 }
 
-impl Buffer {
-    pub fn create_buffer_from_file(path: &str) -> Buffer {
-        let mut file = fs::File::open(path).expect("Cannot open mp3 file.");
-        let mut data: Vec<u8> = Vec::new();
+impl<'a> Buffer<'a> {
+    pub fn create_buffer_from_file(path: &str) -> Result<Buffer<'a>, ErrorType> {
+        let data = fs::read(path).map_err(ErrorType::Io)?;
 
-        file.read_to_end(&mut data)
-            .expect("Cannot read data from file");
+        Ok(Self::create_buffer_from_bytes(data))
+    }
 
+    pub fn create_buffer_from_bytes(data: Vec<u8>) -> Buffer<'a> {
         let total_bits = data.len() * 8;
 
         Self {
-            data,
+            data: Cow::Owned(data),
             pos: 0,
             total_bits,
         }
     }
 
+    pub fn create_buffer_from_slice(data: &'a [u8]) -> Buffer<'a> {
+        Self {
+            data: Cow::Borrowed(data),
+            pos: 0,
+            total_bits: data.len() * 8,
+        }
+    }
+
+    /// Packs `(value, bits)` fields after `header`, most significant bit first, into `length`
+    /// bytes padded with zeros, such as a hand made frame for a test.
+    #[cfg(test)]
+    pub(crate) fn create_buffer_from_fields(
+        header: &[u8],
+        fields: &[(u32, u32)],
+        length: usize,
+    ) -> Buffer<'a> {
+        let mut bits: Vec<u8> = Vec::new();
+
+        for &(value, length) in fields {
+            bits.extend((0..length).rev().map(|i| (value >> i) as u8 & 1));
+        }
+
+        let mut data = header.to_vec();
+        data.extend(bits.chunks(8).map(|byte| {
+            byte.iter()
+                .enumerate()
+                .fold(0, |acc, (i, bit)| acc | bit << (7 - i))
+        }));
+        data.resize(length, 0);
+
+        Self::create_buffer_from_bytes(data)
+    }
+
     pub fn get_bits(&mut self, n: u32) -> Result<u32, ErrorType> {
         if self.pos + n as usize > self.total_bits {
             return Err(ErrorType::OutOfIndex);
@@ -41,26 +80,150 @@ impl Buffer {
         let start_byte_index = self.pos / 8;
         let end_byte_index = (self.pos + n as usize - 1) / 8;
 
-        let start_offset: u32 = ((0xff >> (self.pos as u8 % 8)) as u32) << (((n - 1) / 8) * 8);
-        let end_offset: u32 = 0xff << (7 - ((self.pos as u32 + n - 1) as u8 % 8));
+        let mut result: u64 = 0;
 
-        let mask: u32 = if start_byte_index == end_byte_index {
-            (start_offset) & (end_offset)
-        } else {
-            (start_offset) | (end_offset)
-        };
+        for byte in &self.data[start_byte_index..=end_byte_index] {
+            result = (result << 8) | *byte as u64;
+        }
+
+        result >>= 7 - ((self.pos + n as usize - 1) % 8);
+        result &= (1 << n) - 1;
+
+        self.move_pos(n as isize);
+
+        Ok(result as u32)
+    }
 
-        let mut result: u32 = 0;
+    /// Returns the length of the frame whose header starts at byte `index`, if that header is valid.
+    pub(crate) fn frame_length_at(&mut self, index: usize) -> Option<(Header, usize)> {
+        let pos = self.pos;
+        self.pos = index * 8;
 
-        for i in 0..=(n as usize - 1) / 8 {
-            result |= (self.data[i + start_byte_index] as u32) << (((n as usize - 1) / 8 - i) * 8)
+        let header = Header::create_from_buffer(self);
+        self.pos = pos;
+
+        let header = header.ok()?;
+        header.validate_header().ok()?;
+        let length = header.get_frame_length().ok()?;
+
+        Some((header, length))
+    }
+
+    /// Moves `pos` to the first frame header at or after the current (byte aligned) position
+    /// that is followed by another valid header with the same version, layer and frequency at
+    /// the computed frame length. A frame that ends exactly at the end of the data is accepted
+    /// on its own.
+    pub fn find_sync(&mut self) -> Result<(), ErrorType> {
+        self.search_sync(true)
+    }
+
+    /// Like `find_sync`, but always requires the following header, for data that more of the
+    /// stream is still to be appended to.
+    pub(crate) fn find_confirmed_sync(&mut self) -> Result<(), ErrorType> {
+        self.search_sync(false)
+    }
+
+    fn search_sync(&mut self, accept_last_frame: bool) -> Result<(), ErrorType> {
+        let mut index = self.pos.div_ceil(8);
+
+        while index + 4 <= self.data.len() {
+            if self.data[index] == 0xff && self.data[index + 1] & 0xe0 == 0xe0 {
+                if let Some((header, length)) = self.frame_length_at(index) {
+                    let next_index = index + length;
+
+                    let is_confirmed = if next_index + 4 > self.data.len() {
+                        accept_last_frame && next_index <= self.data.len()
+                    } else {
+                        self.frame_length_at(next_index)
+                            .is_some_and(|(next_header, _)| {
+                                next_header.version == header.version
+                                    && next_header.layer == header.layer
+                                    && next_header.frequency == header.frequency
+                            })
+                    };
+
+                    if is_confirmed {
+                        self.pos = index * 8;
+                        return Ok(());
+                    }
+                }
+            }
+
+            index += 1;
         }
 
-        result = (result & mask) >> (7 - ((self.pos as u32 + n - 1) as u8 % 8));
+        Err(ErrorType::SyncNotFound)
+    }
 
-        self.move_pos(n as isize);
+    /// Appends up to `length` bytes from `reader`, fewer only when it reaches the end of its
+    /// data. Returns the number of bytes appended.
+    pub(crate) fn read_from(&mut self, reader: &mut dyn Read, length: usize) -> io::Result<usize> {
+        let start = self.data.len();
+
+        reader.take(length as u64).read_to_end(self.data.to_mut())?;
+        self.total_bits = self.data.len() * 8;
+
+        Ok(self.data.len() - start)
+    }
+
+    pub(crate) fn append(&mut self, data: &[u8]) {
+        self.data.to_mut().extend_from_slice(data);
+        self.total_bits = self.data.len() * 8;
+    }
+
+    /// Drops the bytes in front of the byte holding the position, which moves back accordingly.
+    /// Returns the number of bytes dropped.
+    pub(crate) fn discard_consumed(&mut self) -> usize {
+        let consumed = self.pos / 8;
+
+        match &mut self.data {
+            Cow::Borrowed(data) => *data = &data[consumed..],
+            Cow::Owned(data) => {
+                data.drain(..consumed);
+            }
+        }
+
+        self.total_bits = self.data.len() * 8;
+        self.pos -= consumed * 8;
+
+        consumed
+    }
+
+    /// Drops the bytes from `length` on, such as a tag at the end of the file.
+    pub(crate) fn truncate(&mut self, length: usize) {
+        match &mut self.data {
+            Cow::Borrowed(data) => *data = &data[..length.min(data.len())],
+            Cow::Owned(data) => data.truncate(length),
+        }
+
+        self.total_bits = self.data.len() * 8;
+        self.pos = self.pos.min(self.total_bits);
+    }
+
+    /// Bytes missing at the end of the data for `find_confirmed_sync` to accept the first frame
+    /// at or after byte `index` that is not followed by a complete header yet, or `None` when
+    /// there is no such frame header.
+    pub(crate) fn missing_frame_bytes(&mut self, index: usize) -> Option<usize> {
+        (index..self.data.len().saturating_sub(3)).find_map(|index| {
+            let (_, length) = self.frame_length_at(index)?;
+            let end = index + length + 4;
+
+            (end > self.data.len()).then(|| end - self.data.len())
+        })
+    }
+
+    /// Bytes of the stream held by the buffer.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
 
-        Ok(result)
+    /// Position in bits from the start of `data`.
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    pub fn total_bits(&self) -> usize {
+        self.total_bits
     }
 
     pub fn set_pos(&mut self, pos: usize) {
@@ -68,7 +231,7 @@ impl Buffer {
     }
 
     pub fn move_pos(&mut self, n: isize) {
-        if self.pos as isize + n > 0 && self.pos as isize + n < self.total_bits as isize {
+        if self.pos as isize + n >= 0 && self.pos as isize + n <= self.total_bits as isize {
             self.pos = (n + self.pos as isize) as usize;
         }
     }
@@ -82,8 +245,8 @@ mod test {
 
     #[test]
     fn test_header_from_buffer() {
-        let mut buffer = Buffer::create_buffer_from_file("mp3-examples/test_data_1mb.mp3");
-        let header = Header::create_from_buffer(&mut buffer);
+        let mut buffer = Buffer::create_buffer_from_file("mp3-examples/test_data_1mb.mp3").unwrap();
+        let header = Header::create_from_buffer(&mut buffer).unwrap();
 
         assert_eq!(
             header,
@@ -97,8 +260,8 @@ mod test {
                 padding_bit: false,
                 private_bit: false,
                 mode: Mode::JointStereo,
-                intensity_stereo: true,
-                ms_stereo: false,
+                intensity_stereo: false,
+                ms_stereo: true,
                 copy_right: false,
                 copy_of_original: false,
                 emphasis: 0,
@@ -107,20 +270,62 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_find_sync() {
+        let mut buffer = Buffer::create_buffer_from_file("mp3-examples/test_data_1mb.mp3").unwrap();
+
+        buffer.find_sync().unwrap();
+        assert_eq!(buffer.pos, 0);
+
+        // Starting inside the first frame skips to the second one.
+        buffer.set_pos(8);
+        buffer.find_sync().unwrap();
+        assert_eq!(buffer.pos, 417 * 8);
+
+        let mut buffer = Buffer {
+            data: vec![0xff, 0xfb, 0x90, 0x64, 0, 0, 0, 0].into(),
+            pos: 0,
+            total_bits: 64,
+        };
+        assert!(buffer.find_sync().is_err());
+    }
+
     #[test]
     fn test_get_buffer_from_file_1mb() {
-        let buffer = Buffer::create_buffer_from_file("mp3-examples/test_data_1mb.mp3");
+        let buffer = Buffer::create_buffer_from_file("mp3-examples/test_data_1mb.mp3").unwrap();
 
         assert_eq!(buffer.data.len() / (1024 * 1024), 1);
     }
 
+    #[test]
+    fn test_get_buffer_from_missing_file() {
+        assert!(matches!(
+            Buffer::create_buffer_from_file("mp3-examples/missing.mp3"),
+            Err(ErrorType::Io(_))
+        ));
+    }
+
     #[test]
     fn test_get_bits() {
-        let mut buffer = Buffer::create_buffer_from_file("mp3-examples/test_data_1mb.mp3");
+        let mut buffer = Buffer::create_buffer_from_file("mp3-examples/test_data_1mb.mp3").unwrap();
 
         assert_eq!(buffer.get_bits(12).unwrap(), 0xfff);
         assert_eq!(buffer.get_bits(1).unwrap(), 1); // MPEG-1
         assert_eq!(buffer.get_bits(2).unwrap(), 1); // LAYER III
         assert_eq!(buffer.get_bits(1).unwrap(), 1); // Error Protection
     }
+
+    #[test]
+    fn test_borrowed_slice() {
+        let data = [0x12, 0x34, 0x56, 0x78];
+        let mut buffer = Buffer::create_buffer_from_slice(&data);
+
+        assert_eq!(buffer.get_bits(12).unwrap(), 0x123);
+
+        buffer.truncate(3);
+        assert_eq!(buffer.discard_consumed(), 1);
+        assert_eq!(buffer.get_bits(12).unwrap(), 0x456);
+        assert!(matches!(buffer.data, Cow::Borrowed([0x34, 0x56])));
+        assert_eq!(buffer.total_bits, 16);
+    }
 }
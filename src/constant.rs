@@ -1,6 +1,111 @@
 pub const HALF_BITRATE_MPEG1_LAYER3: [u8; 15] =
     [0, 16, 20, 24, 28, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160];
 
+pub const BITRATE_MPEG1_LAYER1: [u16; 15] = [
+    0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448,
+];
+
+pub const BITRATE_MPEG2_LAYER1: [u16; 15] = [
+    0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256,
+];
+
+pub const BITRATE_MPEG1_LAYER2: [u16; 15] = [
+    0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384,
+];
+
+/// MPEG-2 and 2.5 use the same bitrates for Layer II and III.
+pub const BITRATE_MPEG2_LAYER2_3: [u8; 15] =
+    [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+
 pub const FREQUENCY_MPEG1: [u16; 3] = [44100, 48000, 32000];
 
+pub const FREQUENCY_MPEG2: [u16; 3] = [22050, 24000, 16000];
+
+pub const FREQUENCY_MPEG2_5: [u16; 3] = [11025, 12000, 8000];
+
 pub const FRAME_SMAPLES: u16 = 1152;
+
+pub const FRAME_SAMPLES_LAYER1: u16 = 384;
+
+/// Indexed by `Header::get_sample_rate_index`, MPEG-1 first, then MPEG-2 and MPEG-2.5.
+pub const SCALEFACTOR_BAND_LONG: [[u16; 23]; 9] = [
+    [
+        0, 4, 8, 12, 16, 20, 24, 30, 36, 44, 52, 62, 74, 90, 110, 134, 162, 196, 238, 288, 342,
+        418, 576,
+    ],
+    [
+        0, 4, 8, 12, 16, 20, 24, 30, 36, 42, 50, 60, 72, 88, 106, 128, 156, 190, 230, 276, 330,
+        384, 576,
+    ],
+    [
+        0, 4, 8, 12, 16, 20, 24, 30, 36, 44, 54, 66, 82, 102, 126, 156, 194, 240, 296, 364, 448,
+        550, 576,
+    ],
+    [
+        0, 6, 12, 18, 24, 30, 36, 44, 54, 66, 80, 96, 116, 140, 168, 200, 238, 284, 336, 396, 464,
+        522, 576,
+    ],
+    [
+        0, 6, 12, 18, 24, 30, 36, 44, 54, 66, 80, 96, 114, 136, 162, 194, 232, 278, 332, 394, 464,
+        540, 576,
+    ],
+    [
+        0, 6, 12, 18, 24, 30, 36, 44, 54, 66, 80, 96, 116, 140, 168, 200, 238, 284, 336, 396, 464,
+        522, 576,
+    ],
+    [
+        0, 6, 12, 18, 24, 30, 36, 44, 54, 66, 80, 96, 116, 140, 168, 200, 238, 284, 336, 396, 464,
+        522, 576,
+    ],
+    [
+        0, 6, 12, 18, 24, 30, 36, 44, 54, 66, 80, 96, 116, 140, 168, 200, 238, 284, 336, 396, 464,
+        522, 576,
+    ],
+    [
+        0, 12, 24, 36, 48, 60, 72, 88, 108, 132, 160, 192, 232, 280, 336, 400, 476, 566, 568, 570,
+        572, 574, 576,
+    ],
+];
+
+pub const SCALEFACTOR_BAND_SHORT: [[u16; 14]; 9] = [
+    [0, 4, 8, 12, 16, 22, 30, 40, 52, 66, 84, 106, 136, 192],
+    [0, 4, 8, 12, 16, 22, 28, 38, 50, 64, 80, 100, 126, 192],
+    [0, 4, 8, 12, 16, 22, 30, 42, 58, 78, 104, 138, 180, 192],
+    [0, 4, 8, 12, 18, 24, 32, 42, 56, 74, 100, 132, 174, 192],
+    [0, 4, 8, 12, 18, 26, 36, 48, 62, 80, 104, 136, 180, 192],
+    [0, 4, 8, 12, 18, 26, 36, 48, 62, 80, 104, 134, 174, 192],
+    [0, 4, 8, 12, 18, 26, 36, 48, 62, 80, 104, 134, 174, 192],
+    [0, 4, 8, 12, 18, 26, 36, 48, 62, 80, 104, 134, 174, 192],
+    [0, 8, 16, 24, 36, 52, 72, 96, 124, 160, 162, 164, 166, 192],
+];
+
+/// Number of scalefactors read with each of the four slen values of an MPEG-2 LSF granule,
+/// indexed by the `scalefac_compress` range (the last three for the intensity stereo channel)
+/// and by long, short or mixed block.
+pub const SCALEFACTOR_COUNT_LSF: [[[u8; 4]; 3]; 6] = [
+    [[6, 5, 5, 5], [9, 9, 9, 9], [6, 9, 9, 9]],
+    [[6, 5, 7, 3], [9, 9, 12, 6], [6, 9, 12, 6]],
+    [[11, 10, 0, 0], [18, 18, 0, 0], [15, 18, 0, 0]],
+    [[7, 7, 7, 0], [12, 12, 12, 0], [6, 15, 12, 0]],
+    [[6, 6, 6, 3], [12, 9, 9, 6], [6, 12, 9, 6]],
+    [[8, 8, 5, 0], [15, 12, 9, 0], [6, 18, 9, 0]],
+];
+
+/// Long bands a mixed block starts with, before it continues with short band 3.
+pub fn mixed_block_long_bands(sample_rate_index: usize) -> usize {
+    if sample_rate_index < 3 {
+        8
+    } else {
+        6
+    }
+}
+
+/// Subbands a mixed block keeps as long blocks: 2, or 4 at 8 kHz where short band 3 starts at
+/// line 72.
+pub fn mixed_block_long_subbands(sample_rate_index: usize) -> usize {
+    SCALEFACTOR_BAND_SHORT[sample_rate_index][3] as usize * 3 / 18
+}
+
+pub const PRETAB: [u8; 22] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 3, 3, 3, 2, 0,
+];
@@ -0,0 +1,59 @@
+const CRC_POLYNOMIAL: u16 = 0x8005;
+
+/// MPEG audio CRC-16 (polynomial 0x8005, initial value 0xffff) over the first `bits` bits of
+/// `data`, most significant bit first.
+pub fn crc16(data: &[u8], bits: usize) -> u16 {
+    let mut crc: u16 = 0xffff;
+
+    for bit_index in 0..bits {
+        let bit = (data[bit_index / 8] >> (7 - bit_index % 8)) & 1;
+        let top = (crc >> 15) as u8 & 1;
+
+        crc <<= 1;
+
+        if top ^ bit == 1 {
+            crc ^= CRC_POLYNOMIAL;
+        }
+    }
+
+    crc
+}
+
+/// CRC-16/ARC (reflected polynomial 0xa001, initial value 0), which LAME uses for its tag and
+/// for the music data.
+pub fn crc16_arc(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+
+    for &byte in data {
+        crc ^= byte as u16;
+
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xa001
+            } else {
+                crc >> 1
+            };
+        }
+    }
+
+    crc
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_crc16() {
+        assert_eq!(crc16(b"123456789", 72), 0xaee7);
+        assert_eq!(crc16(&[], 0), 0xffff);
+        // Only the first 4 bits of 0xf0 are covered, the same as a 0xff byte cut to 4 bits.
+        assert_eq!(crc16(&[0xf0], 4), crc16(&[0xff], 4));
+    }
+
+    #[test]
+    fn test_crc16_arc() {
+        assert_eq!(crc16_arc(b"123456789"), 0xbb3d);
+        assert_eq!(crc16_arc(&[]), 0);
+    }
+}
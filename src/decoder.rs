@@ -0,0 +1,1319 @@
+use std::{fmt, io::Read};
+
+use crate::{
+    ape::{self, ApeTag},
+    buffer::Buffer,
+    error::ErrorType,
+    frame::Frame,
+    header::{Header, Layer},
+    id3::{self, Id3v1Tag, Id3v2Tag},
+    imdct::Imdct,
+    layer1::Layer1Frame,
+    layer2::Layer2Frame,
+    reservoir::Reservoir,
+    synthesis::{self, Synthesis},
+    vbr::VbrInfo,
+};
+
+/// What the decoder does with error protected frames whose CRC does not match. Whatever the
+/// policy, `DecodedFrame::crc_valid` tells whether the CRC matched.
+#[derive(Default)]
+pub enum CrcPolicy<'a> {
+    /// Decode the frame as if the CRC matched.
+    #[default]
+    Ignore,
+    /// Decode the frame and pass its byte position in the stream to the callback, such as a
+    /// logger or an error counter.
+    Log(Box<dyn FnMut(u64) + 'a>),
+    /// Replace the frame with silence.
+    Conceal,
+}
+
+impl fmt::Debug for CrcPolicy<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ignore => write!(f, "Ignore"),
+            Self::Log(_) => write!(f, "Log(..)"),
+            Self::Conceal => write!(f, "Conceal"),
+        }
+    }
+}
+
+/// PCM output of one decoded frame.
+#[derive(Debug)]
+pub struct DecodedFrame {
+    /// Interleaved samples in the -1.0..1.0 range.
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+    pub channels: usize,
+    /// Bytes skipped since the previous frame while looking for a valid one.
+    pub discarded_bytes: usize,
+    /// Whether the CRC matched, or `None` for frames without error protection.
+    pub crc_valid: Option<bool>,
+}
+
+impl DecodedFrame {
+    /// Interleaved samples clipped to 16 bits.
+    pub fn samples_i16(&self) -> Vec<i16> {
+        self.samples
+            .iter()
+            .map(|&sample| synthesis::to_i16(sample))
+            .collect()
+    }
+}
+
+/// Samples per channel the output of a Layer III decoder lags behind the encoder input, which
+/// gapless trimming adds to the encoder delay.
+const DECODER_DELAY: u64 = 529;
+
+/// Longest possible frame in bytes: Layer II at 384 kbit/s and 32 kHz, with padding.
+const MAX_FRAME_LENGTH: usize = 1729;
+
+/// Bytes a streaming decoder keeps ahead of the position, enough for the sync search to confirm
+/// a header by the one that follows it.
+const STREAM_LOOKAHEAD: usize = 8 * 1024;
+
+/// Bytes a streaming decoder reads at once when the lookahead runs short.
+const STREAM_CHUNK: usize = 16 * 1024;
+
+/// Where the data that follows the buffer comes from.
+enum Input<'a> {
+    /// Nowhere, the buffer holds the rest of the stream.
+    Complete,
+    /// A reader, until it is exhausted.
+    Reader(Box<dyn Read + 'a>),
+    /// `Decoder::feed`, until `Decoder::finish` is called.
+    Fed,
+}
+
+/// Decodes the frames of a `Buffer` one after another, keeping the state that has to survive
+/// from one granule (and frame) to the next.
+pub struct Decoder<'a> {
+    buffer: Buffer<'a>,
+    input: Input<'a>,
+    /// Whether the tags at the start of the stream were read.
+    started: bool,
+    /// Whether the previous frame was decoded, so that the next one is taken on its own header.
+    locked: bool,
+    /// Bytes that have to be fed before the next frame can be decoded, at least.
+    bytes_needed: usize,
+    reservoir: Reservoir,
+    imdct: [Imdct; 2],
+    synthesis: [Synthesis; 2],
+    discarded_bytes: usize,
+    /// Position in the stream of the first byte of the buffer.
+    stream_offset: u64,
+    /// Bytes skipped while looking for the next frame before the fed data ran out.
+    pending_discarded_bytes: usize,
+    crc_policy: CrcPolicy<'a>,
+    id3v2: Option<Id3v2Tag>,
+    id3v1: Option<Id3v1Tag>,
+    ape: Option<ApeTag>,
+    /// APEv2 tag inside the stream of a streaming decoder and its whole length, read apart
+    /// from the buffer so that the sync search does not look into it.
+    pending_ape: Option<(Vec<u8>, usize)>,
+    vbr_info: Option<VbrInfo>,
+    /// Start and end bit position of the frame holding the VBR tag, skipped instead of decoded.
+    vbr_frame: Option<(usize, usize)>,
+    gapless: bool,
+    encoder_delay: Option<u64>,
+    end_padding: Option<u64>,
+    /// Length of the encoder input in samples, from iTunSMPB.
+    original_samples: Option<u64>,
+    /// Samples of the whole stream, counted once the input is complete when no tag holds the
+    /// frame count.
+    stream_samples: Option<u64>,
+    /// Samples per channel decoded so far, before trimming.
+    position: u64,
+}
+
+impl<'a> Decoder<'a> {
+    /// Creates a decoder reading frames from the current position of `buffer`.
+    pub fn new(buffer: Buffer<'a>) -> Self {
+        let mut decoder = Self::create(buffer, Input::Complete);
+
+        decoder.read_trailing_tags();
+        decoder.read_leading_tags();
+        decoder
+    }
+
+    /// Creates a decoder pulling data from `reader` as frames are decoded, such as a file, a
+    /// pipe or a socket. Only a few frames are held in memory at a time, apart from an ID3v2 tag
+    /// at the start and APEv2 tags with a header, which are read as a whole. The other tags at
+    /// the end are read once the reader is exhausted.
+    pub fn from_reader(reader: impl Read + 'a) -> Result<Self, ErrorType> {
+        let mut decoder = Self::create(
+            Buffer::create_buffer_from_bytes(Vec::new()),
+            Input::Reader(Box::new(reader)),
+        );
+
+        decoder.start()?;
+        Ok(decoder)
+    }
+
+    /// Creates a decoder for a stream that arrives in chunks, such as network packets. The
+    /// chunks are passed to `feed`, after which `decode_available` returns the frames they
+    /// completed.
+    pub fn incremental() -> Self {
+        let mut decoder = Self::create(Buffer::create_buffer_from_bytes(Vec::new()), Input::Fed);
+
+        decoder.bytes_needed = STREAM_LOOKAHEAD;
+        decoder
+    }
+
+    fn create(buffer: Buffer<'a>, input: Input<'a>) -> Self {
+        Self {
+            buffer,
+            input,
+            started: false,
+            locked: false,
+            bytes_needed: 0,
+            reservoir: Reservoir::new(),
+            imdct: [Imdct::new(), Imdct::new()],
+            synthesis: [Synthesis::new(), Synthesis::new()],
+            discarded_bytes: 0,
+            stream_offset: 0,
+            pending_discarded_bytes: 0,
+            crc_policy: CrcPolicy::default(),
+            id3v2: None,
+            id3v1: None,
+            ape: None,
+            pending_ape: None,
+            vbr_info: None,
+            vbr_frame: None,
+            gapless: true,
+            encoder_delay: None,
+            end_padding: None,
+            original_samples: None,
+            stream_samples: None,
+            position: 0,
+        }
+    }
+
+    /// Appends a chunk of the stream to the buffer of an `incremental` decoder. Chunks may split
+    /// frames anywhere, the partial frame at the end waits for the next chunk.
+    pub fn feed(&mut self, mut data: &[u8]) {
+        self.discard_consumed();
+
+        if let Some((tag, length)) = &mut self.pending_ape {
+            let (rest_of_tag, after_tag) = data.split_at((*length - tag.len()).min(data.len()));
+
+            tag.extend_from_slice(rest_of_tag);
+            data = after_tag;
+        }
+
+        let start = self.buffer.data.len();
+
+        self.buffer.append(data);
+        self.divert_ape_tag(start);
+    }
+
+    /// Decodes every frame the chunks fed so far complete. Afterwards, `bytes_needed` tells how
+    /// many bytes the next frame is missing.
+    pub fn decode_available(&mut self) -> Result<Vec<DecodedFrame>, ErrorType> {
+        let mut frames = Vec::new();
+
+        while let Some(frame) = self.next_frame()? {
+            frames.push(frame);
+        }
+
+        Ok(frames)
+    }
+
+    /// Marks the end of the fed stream, so that the last frame no longer waits for a following
+    /// header and the tags at the end are cut off. The remaining frames are returned by
+    /// `decode_available`.
+    pub fn finish(&mut self) {
+        if matches!(self.input, Input::Fed) {
+            self.input = Input::Complete;
+            self.bytes_needed = 0;
+            self.pending_ape = None;
+            self.read_trailing_tags();
+        }
+    }
+
+    /// Bytes that have to be fed before `decode_available` can return another frame, at least.
+    /// Until the start of the stream is read, these include the whole ID3v2 tag and
+    /// `STREAM_LOOKAHEAD` bytes to look for the VBR tag in.
+    pub fn bytes_needed(&self) -> usize {
+        self.bytes_needed
+    }
+
+    /// Reads the tags at the start of the stream once the buffer holds the whole ID3v2 tag and
+    /// `STREAM_LOOKAHEAD` bytes after it, or the rest of the stream. Returns whether it did.
+    fn start(&mut self) -> Result<bool, ErrorType> {
+        loop {
+            let available = &self.buffer.data[self.buffer.pos / 8..];
+            let tag_length = Id3v2Tag::length_from_header(available).unwrap_or(0);
+            let missing = (tag_length + STREAM_LOOKAHEAD).saturating_sub(available.len());
+
+            match self.input {
+                Input::Complete => break,
+                _ if missing == 0 => break,
+                Input::Reader(_) => self.read_input(missing)?,
+                Input::Fed => {
+                    self.bytes_needed = missing;
+                    return Ok(false);
+                }
+            }
+        }
+
+        self.read_leading_tags();
+        Ok(true)
+    }
+
+    fn read_leading_tags(&mut self) {
+        self.started = true;
+        self.read_id3v2();
+        self.divert_ape_tag(0);
+        self.read_vbr_info();
+        self.read_encoder_delay();
+    }
+
+    /// Tops up the buffer of a streaming decoder so that `STREAM_LOOKAHEAD` bytes follow the
+    /// position.
+    fn fill(&mut self) -> Result<(), ErrorType> {
+        if !matches!(self.input, Input::Reader(_))
+            || self.buffer.data.len() - self.buffer.pos / 8 >= STREAM_LOOKAHEAD
+        {
+            return Ok(());
+        }
+
+        self.discard_consumed();
+        self.read_input(STREAM_LOOKAHEAD + STREAM_CHUNK - self.buffer.data.len())
+    }
+
+    /// Appends up to `length` bytes from the reader. Once it is exhausted, the tags at the end
+    /// of the stream are cut off.
+    fn read_input(&mut self, length: usize) -> Result<(), ErrorType> {
+        let Input::Reader(reader) = &mut self.input else {
+            return Ok(());
+        };
+
+        let start = self.buffer.data.len();
+        let mut exhausted = self
+            .buffer
+            .read_from(reader.as_mut(), length)
+            .map_err(ErrorType::Io)?
+            < length;
+
+        self.divert_ape_tag(start);
+
+        // The rest of an APE tag is read right away, the buffer is topped up after it.
+        while let (Input::Reader(reader), Some((tag, tag_length)), false) =
+            (&mut self.input, &mut self.pending_ape, exhausted)
+        {
+            let missing = *tag_length - tag.len();
+
+            exhausted = reader
+                .by_ref()
+                .take(missing as u64)
+                .read_to_end(tag)
+                .map_err(ErrorType::Io)?
+                < missing;
+
+            self.divert_ape_tag(self.buffer.data.len());
+        }
+
+        if exhausted {
+            self.input = Input::Complete;
+            self.pending_ape = None;
+            self.read_trailing_tags();
+        }
+
+        Ok(())
+    }
+
+    /// Moves an APEv2 tag whose header starts at or after byte `from` out of the buffer of a
+    /// streaming decoder, and reads it once `pending_ape` holds all of it. The footer of a tag
+    /// larger than the buffer would only be seen after the sync search went through the tag.
+    /// Tags without a header are cut off at the end of the stream, when they fit in the buffer.
+    fn divert_ape_tag(&mut self, mut from: usize) {
+        if !self.started || matches!(self.input, Input::Complete) {
+            return;
+        }
+
+        loop {
+            if let Some((tag, length)) = &mut self.pending_ape {
+                if tag.len() < *length {
+                    return;
+                }
+
+                let after_tag = tag.split_off(*length);
+
+                if let Some(tag) = ApeTag::create_from_bytes(tag) {
+                    self.ape.get_or_insert(tag);
+                }
+
+                self.pending_ape = None;
+                from = from.min(self.buffer.data.len());
+                self.buffer.append(&after_tag);
+            }
+
+            let data = &self.buffer.data;
+            let start = from
+                .saturating_sub(ape::FOOTER_LENGTH - 1)
+                .max(self.buffer.pos.div_ceil(8));
+
+            let Some((index, length)) = (start..data.len())
+                .find_map(|index| Some((index, ApeTag::length_from_header(&data[index..])?)))
+            else {
+                return;
+            };
+
+            self.pending_ape = Some((data[index..].to_vec(), length));
+            self.buffer.truncate(index);
+        }
+    }
+
+    /// Drops the bytes in front of the position, which keeps the buffer of a streaming decoder
+    /// bounded.
+    fn discard_consumed(&mut self) {
+        let consumed = self.buffer.discard_consumed();
+        self.stream_offset += consumed as u64;
+
+        let consumed = consumed * 8;
+
+        self.vbr_frame = self
+            .vbr_frame
+            .and_then(|(start, end)| Some((start.checked_sub(consumed)?, end - consumed)));
+    }
+
+    /// Takes the encoder delay and end padding from the LAME tag, or else from the iTunSMPB
+    /// comment of the ID3v2 tag.
+    fn read_encoder_delay(&mut self) {
+        if let Some(vbr_info) = &self.vbr_info {
+            self.encoder_delay = vbr_info.encoder_delay().map(u64::from);
+            self.end_padding = vbr_info.end_padding().map(u64::from);
+        }
+
+        if self.encoder_delay.is_none() {
+            if let Some((encoder_delay, end_padding, samples)) =
+                self.id3v2.as_ref().and_then(Id3v2Tag::itunes_gapless)
+            {
+                self.set_encoder_delay(encoder_delay, end_padding);
+                self.original_samples = samples;
+            }
+        }
+    }
+
+    /// Reads an ID3v2 tag at the current position and moves past it, so that the sync search
+    /// does not look into it.
+    fn read_id3v2(&mut self) {
+        self.id3v2 = Id3v2Tag::create_from_buffer(&self.buffer);
+
+        if let Some(tag) = &self.id3v2 {
+            let end = self.buffer.pos / 8 + tag.length;
+
+            self.buffer.set_pos(end.min(self.buffer.data.len()) * 8);
+        }
+    }
+
+    /// ID3v2 tag in front of the stream.
+    pub fn id3v2(&self) -> Option<&Id3v2Tag> {
+        self.id3v2.as_ref()
+    }
+
+    /// Reads the ID3v1 tag and the APE tags and Lyrics3v2 blocks in front of it at the end of the
+    /// buffer, and cuts them off so that they are not taken for broken frames.
+    fn read_trailing_tags(&mut self) {
+        self.id3v1 = Id3v1Tag::create_from_buffer(&self.buffer);
+
+        if let Some(tag) = &self.id3v1 {
+            self.buffer.truncate(self.buffer.data.len() - tag.length());
+        }
+
+        loop {
+            let length = if let Some(tag) = ApeTag::create_from_buffer(&self.buffer) {
+                let length = tag.length;
+
+                self.ape.get_or_insert(tag);
+                length
+            } else if let Some(length) = id3::lyrics3v2_length(&self.buffer.data) {
+                length
+            } else {
+                break;
+            };
+
+            self.buffer.truncate(self.buffer.data.len() - length);
+        }
+    }
+
+    /// ID3v1 tag at the end of the stream.
+    pub fn id3v1(&self) -> Option<&Id3v1Tag> {
+        self.id3v1.as_ref()
+    }
+
+    /// APE tag at the end of the stream.
+    pub fn ape(&self) -> Option<&ApeTag> {
+        self.ape.as_ref()
+    }
+
+    /// Looks for a Xing/Info or VBRI tag in the first frame, leaving the buffer position untouched.
+    fn read_vbr_info(&mut self) {
+        let pos = self.buffer.pos;
+
+        if self.buffer.find_sync().is_ok() {
+            if let Ok(header) = Header::create_from_buffer(&mut self.buffer) {
+                self.vbr_info = VbrInfo::create_from_buffer(&self.buffer, &header);
+
+                if let (Some(_), Ok(length)) = (&self.vbr_info, header.get_frame_length()) {
+                    self.vbr_frame = Some((header.pos, header.pos + length * 8));
+                }
+            }
+        }
+
+        self.buffer.set_pos(pos);
+    }
+
+    /// VBR tag of the stream. The frame holding it carries no audio and is not returned by
+    /// `next_frame`.
+    pub fn vbr_info(&self) -> Option<&VbrInfo> {
+        self.vbr_info.as_ref()
+    }
+
+    /// Enables or disables gapless output, on by default. The encoder delay and the decoder delay
+    /// are dropped from the start, and the end padding from the end when the length of the stream
+    /// is known from the VBR tag, the iTunSMPB comment or complete input, so the output is as
+    /// long as the encoder input.
+    pub fn set_gapless(&mut self, gapless: bool) {
+        self.gapless = gapless;
+    }
+
+    /// Overrides the encoder delay and end padding read from the LAME tag, for example with the
+    /// values of iTunSMPB metadata.
+    pub fn set_encoder_delay(&mut self, encoder_delay: u32, end_padding: u32) {
+        self.encoder_delay = Some(encoder_delay as u64);
+        self.end_padding = Some(end_padding as u64);
+        self.original_samples = None;
+    }
+
+    /// Drops the samples of `frame` that fall before the encoder delay or after the end padding.
+    fn trim(&mut self, frame: &mut DecodedFrame) {
+        let length = (frame.samples.len() / frame.channels) as u64;
+        let start = self.position;
+        self.position += length;
+
+        let Some(encoder_delay) = self.encoder_delay.filter(|_| self.gapless) else {
+            return;
+        };
+
+        let first = encoder_delay + DECODER_DELAY;
+
+        let last = match (self.original_samples, self.end_padding) {
+            (Some(samples), _) => first + samples,
+            (None, Some(end_padding)) => {
+                let total = self
+                    .vbr_info
+                    .as_ref()
+                    .and_then(VbrInfo::samples)
+                    .or_else(|| self.count_stream_samples());
+
+                total.map_or(u64::MAX, |total| {
+                    (total + DECODER_DELAY)
+                        .saturating_sub(end_padding)
+                        .max(first)
+                })
+            }
+            (None, None) => u64::MAX,
+        };
+
+        let keep_start = (first.clamp(start, start + length) - start) as usize;
+        let keep_end = (last.clamp(start, start + length) - start) as usize;
+
+        frame.samples.truncate(keep_end * frame.channels);
+        frame.samples.drain(..keep_start * frame.channels);
+    }
+
+    /// Number of samples in the stream, which is known once the input is complete: the samples
+    /// decoded so far and those of the frames left in the buffer. Called after `position` has
+    /// been moved past the current frame.
+    fn count_stream_samples(&mut self) -> Option<u64> {
+        if !matches!(self.input, Input::Complete) {
+            return None;
+        }
+
+        if let Some(samples) = self.stream_samples {
+            return Some(samples);
+        }
+
+        let pos = self.buffer.pos;
+        let mut samples = self.position;
+
+        while self.buffer.find_sync().is_ok() {
+            let index = self.buffer.pos / 8;
+            let Some((header, length)) = self.buffer.frame_length_at(index) else {
+                break;
+            };
+
+            if self
+                .vbr_frame
+                .is_none_or(|(start, _)| start != self.buffer.pos)
+            {
+                samples += header.get_frame_samples() as u64;
+            }
+
+            self.buffer.set_pos((index + length) * 8);
+        }
+
+        self.buffer.set_pos(pos);
+        self.stream_samples = Some(samples);
+        self.stream_samples
+    }
+
+    /// Sets how frames with a CRC mismatch are handled, `CrcPolicy::Ignore` by default.
+    pub fn set_crc_policy(&mut self, crc_policy: CrcPolicy<'a>) {
+        self.crc_policy = crc_policy;
+    }
+
+    /// Total number of bytes skipped so far because they did not belong to a valid frame.
+    pub fn discarded_bytes(&self) -> usize {
+        self.discarded_bytes
+    }
+
+    fn imdct(&mut self, frame: &mut Frame) {
+        let channels = frame.header.get_channels();
+        let sample_rate_index = frame.header.get_sample_rate_index();
+
+        for (index, (granule, data)) in frame
+            .side_info
+            .granule_channels
+            .iter()
+            .zip(frame.granules_data.iter_mut())
+            .enumerate()
+        {
+            data.subband_samples =
+                self.imdct[index % channels].process(granule, sample_rate_index, &data.spectrum);
+        }
+    }
+
+    fn synthesize(&mut self, frame: &Frame) -> Vec<f32> {
+        let channels = frame.header.get_channels();
+        let mut pcm = vec![0f32; frame.header.get_frame_samples() * channels];
+
+        for (index, data) in frame.granules_data.iter().enumerate() {
+            let granule_index = index / channels;
+            let channel_index = index % channels;
+            let samples = self.synthesis[channel_index].process(&data.subband_samples);
+
+            for (i, sample) in samples.into_iter().enumerate() {
+                pcm[(granule_index * 576 + i) * channels + channel_index] = sample;
+            }
+        }
+
+        pcm
+    }
+
+    /// Checks the CRC of an error protected frame and applies the `CrcPolicy`, returning whether
+    /// the frame has to be concealed.
+    fn apply_crc_policy(&mut self, crc_valid: Option<bool>, header: &Header) -> bool {
+        if crc_valid != Some(false) {
+            return false;
+        }
+
+        if let CrcPolicy::Log(log) = &mut self.crc_policy {
+            log(self.stream_offset + (header.pos / 8) as u64);
+        }
+
+        matches!(self.crc_policy, CrcPolicy::Conceal)
+    }
+
+    fn decode_layer3_frame(&mut self, header: Header) -> Result<DecodedFrame, ErrorType> {
+        let mut frame = Frame::create_from_header(&mut self.buffer, header)?;
+        let next_frame_pos = frame.header.pos + frame.length_byte * 8;
+
+        if next_frame_pos > self.buffer.total_bits {
+            return Err(ErrorType::OutOfIndex);
+        }
+
+        let crc_valid = frame.check_crc(&self.buffer);
+        let conceal = self.apply_crc_policy(crc_valid, &frame.header);
+
+        let frame_data = &self.buffer.data[self.buffer.pos / 8..next_frame_pos / 8];
+        let main_data_begin = frame.side_info.main_data_begin as usize;
+
+        // Without the referenced reservoir bytes (or with a concealed CRC mismatch) the frame is
+        // decoded as silence, which keeps the IMDCT and synthesis state running.
+        if let Some(mut main_data) = self.reservoir.main_data(main_data_begin, frame_data) {
+            if !conceal {
+                frame.decode_main_data(&mut main_data)?;
+            }
+        }
+
+        frame.requantize();
+        frame.process_stereo();
+        frame.reorder_and_reduce_aliases();
+
+        self.imdct(&mut frame);
+        let samples = self.synthesize(&frame);
+
+        self.buffer.set_pos(next_frame_pos);
+
+        Ok(DecodedFrame {
+            samples,
+            sample_rate: frame.header.get_frequency()? as u32,
+            channels: frame.header.get_channels(),
+            discarded_bytes: 0,
+            crc_valid,
+        })
+    }
+
+    /// Runs the subband samples of each channel, laid out as `subband * slots + slot`, through the
+    /// synthesis filterbank and interleaves the resulting PCM.
+    fn synthesize_slots<T: AsRef<[f32]>>(&mut self, subband_samples: &[T]) -> Vec<f32> {
+        let channels = subband_samples.len();
+        let length = subband_samples
+            .first()
+            .map_or(0, |samples| samples.as_ref().len());
+
+        let mut samples = vec![0f32; length * channels];
+        let mut pcm = vec![0f32; length];
+
+        for (channel_index, channel_samples) in subband_samples.iter().enumerate() {
+            self.synthesis[channel_index].process_slots(channel_samples.as_ref(), &mut pcm);
+
+            for (i, &sample) in pcm.iter().enumerate() {
+                samples[i * channels + channel_index] = sample;
+            }
+        }
+
+        samples
+    }
+
+    fn decode_layer1_frame(&mut self, header: Header) -> Result<DecodedFrame, ErrorType> {
+        let frame = Layer1Frame::create_from_header(&mut self.buffer, header)?;
+        let next_frame_pos = frame.header.pos + frame.length_byte * 8;
+
+        if next_frame_pos > self.buffer.total_bits {
+            return Err(ErrorType::OutOfIndex);
+        }
+
+        let crc_valid = frame.check_crc(&self.buffer);
+        let channels = frame.header.get_channels();
+
+        let subband_samples = if self.apply_crc_policy(crc_valid, &frame.header) {
+            vec![[0f32; 384]; channels]
+        } else {
+            frame.decode_samples(&mut self.buffer)?
+        };
+
+        let samples = self.synthesize_slots(&subband_samples);
+        self.buffer.set_pos(next_frame_pos);
+
+        Ok(DecodedFrame {
+            samples,
+            sample_rate: frame.header.get_frequency()? as u32,
+            channels,
+            discarded_bytes: 0,
+            crc_valid,
+        })
+    }
+
+    fn decode_layer2_frame(&mut self, header: Header) -> Result<DecodedFrame, ErrorType> {
+        let frame = Layer2Frame::create_from_header(&mut self.buffer, header)?;
+        let next_frame_pos = frame.header.pos + frame.length_byte * 8;
+
+        if next_frame_pos > self.buffer.total_bits {
+            return Err(ErrorType::OutOfIndex);
+        }
+
+        let crc_valid = frame.check_crc(&self.buffer);
+        let channels = frame.header.get_channels();
+
+        let subband_samples = if self.apply_crc_policy(crc_valid, &frame.header) {
+            vec![[0f32; 1152]; channels]
+        } else {
+            frame.decode_samples(&mut self.buffer)?
+        };
+
+        let samples = self.synthesize_slots(&subband_samples);
+        self.buffer.set_pos(next_frame_pos);
+
+        Ok(DecodedFrame {
+            samples,
+            sample_rate: frame.header.get_frequency()? as u32,
+            channels,
+            discarded_bytes: 0,
+            crc_valid,
+        })
+    }
+
+    fn decode_frame(&mut self) -> Result<DecodedFrame, ErrorType> {
+        let header = Header::create_from_buffer(&mut self.buffer)?;
+        header.validate_header()?;
+
+        match header.layer {
+            Layer::Layer1 => self.decode_layer1_frame(header),
+            Layer::Layer2 => self.decode_layer2_frame(header),
+            Layer::Layer3 => self.decode_layer3_frame(header),
+        }
+    }
+
+    /// Whether the decoder is in sync and a whole frame with a valid header starts at byte
+    /// `index`. The header that follows is only needed to acquire sync, not to keep it.
+    fn is_locked_frame(&mut self, index: usize) -> bool {
+        self.locked
+            && self
+                .buffer
+                .frame_length_at(index)
+                .is_some_and(|(_, length)| {
+                    matches!(self.input, Input::Complete)
+                        || index + length <= self.buffer.data.len()
+                })
+    }
+
+    /// Decodes the next frame, or returns `None` once the buffer holds no further complete frame.
+    /// Corrupt or truncated frames are skipped by searching for the next confirmed sync word.
+    /// An `incremental` decoder also returns `None` when the fed data ends inside a frame.
+    pub fn next_frame(&mut self) -> Result<Option<DecodedFrame>, ErrorType> {
+        if !self.started && !self.start()? {
+            return Ok(None);
+        }
+
+        let mut discarded_bytes = std::mem::take(&mut self.pending_discarded_bytes);
+
+        loop {
+            self.fill()?;
+
+            let start = self.buffer.pos.div_ceil(8);
+            let synced = if self.is_locked_frame(start) {
+                self.buffer.set_pos(start * 8);
+                Ok(())
+            } else {
+                match self.input {
+                    Input::Complete => self.buffer.find_sync(),
+                    _ => self.buffer.find_confirmed_sync(),
+                }
+            };
+
+            self.locked = false;
+
+            if synced.is_err() {
+                // More data may follow: continue with the bytes that could still start a frame
+                // once the header after it arrives.
+                if !matches!(self.input, Input::Complete) {
+                    let restart = self
+                        .buffer
+                        .data
+                        .len()
+                        .saturating_sub(MAX_FRAME_LENGTH + 4)
+                        .max(start);
+
+                    discarded_bytes += restart - start;
+                    self.buffer.set_pos(restart * 8);
+
+                    if matches!(self.input, Input::Fed) {
+                        self.pending_discarded_bytes = discarded_bytes;
+                        self.bytes_needed = self.buffer.missing_frame_bytes(restart).unwrap_or(1)
+                            + self
+                                .pending_ape
+                                .as_ref()
+                                .map_or(0, |(tag, length)| length - tag.len());
+                        return Ok(None);
+                    }
+
+                    continue;
+                }
+
+                self.discarded_bytes +=
+                    discarded_bytes + self.buffer.data.len().saturating_sub(start);
+                return Ok(None);
+            }
+
+            discarded_bytes += self.buffer.pos / 8 - start;
+            let frame_pos = self.buffer.pos;
+
+            if let Some((start, end)) = self.vbr_frame {
+                if start == frame_pos && end <= self.buffer.total_bits {
+                    self.vbr_frame = None;
+                    self.locked = true;
+                    self.buffer.set_pos(end);
+                    continue;
+                }
+            }
+
+            // Skipped bytes break the chain of main data the reservoir relies on.
+            if discarded_bytes > 0 {
+                self.reservoir.clear();
+            }
+
+            match self.decode_frame() {
+                Ok(mut frame) => {
+                    frame.discarded_bytes = discarded_bytes;
+                    self.discarded_bytes += discarded_bytes;
+                    self.locked = true;
+                    self.trim(&mut frame);
+
+                    return Ok(Some(frame));
+                }
+                Err(_) => {
+                    self.buffer.set_pos(frame_pos + 8);
+                    discarded_bytes += 1;
+                }
+            }
+        }
+    }
+}
+
+impl Iterator for Decoder<'_> {
+    type Item = DecodedFrame;
+
+    /// Yields frames until the end of the buffer or the first decoding error.
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_frame().ok().flatten()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{borrow::Cow, cell::Cell, fs, io};
+
+    use crate::{ape::ApeValue, crc};
+
+    use super::*;
+
+    /// Hands out the data in small pieces, like a pipe or a socket.
+    struct ChunkedReader {
+        data: Vec<u8>,
+        pos: usize,
+    }
+
+    impl Read for ChunkedReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let length = buf.len().min(1000).min(self.data.len() - self.pos);
+
+            buf[..length].copy_from_slice(&self.data[self.pos..self.pos + length]);
+            self.pos += length;
+
+            Ok(length)
+        }
+    }
+
+    #[test]
+    fn test_next_frame() {
+        let buffer = Buffer::create_buffer_from_file("mp3-examples/test_data_100kb.mp3").unwrap();
+
+        let mut decoder = Decoder::new(buffer);
+        decoder.set_gapless(false);
+        let frame = decoder.next_frame().unwrap().unwrap();
+
+        assert_eq!(frame.sample_rate, 44100);
+        assert_eq!(frame.channels, 2);
+        assert_eq!(frame.samples.len(), 2304);
+        assert_eq!(frame.samples_i16().len(), 2304);
+        assert_eq!(frame.discarded_bytes, 0);
+    }
+
+    /// The 100kb sample with its first audio frame (at byte 417, after the silent Xing frame)
+    /// turned into an error protected one. The last two bytes of the frame make room for the CRC.
+    fn protected_buffer(crc_error: u16) -> Buffer<'static> {
+        let mut buffer =
+            Buffer::create_buffer_from_file("mp3-examples/test_data_100kb.mp3").unwrap();
+        let data = buffer.data.to_mut();
+
+        data[418] &= !1;
+
+        let mut protected = data[419..421].to_vec();
+        protected.extend_from_slice(&data[421..453]);
+        let crc = crc::crc16(&protected, protected.len() * 8) ^ crc_error;
+
+        data.drain(1041..1043);
+        data.splice(421..421, crc.to_be_bytes());
+
+        buffer
+    }
+
+    #[test]
+    fn test_crc() {
+        let mut decoder = Decoder::new(protected_buffer(0));
+
+        let frame = decoder.next_frame().unwrap().unwrap();
+        assert_eq!(frame.crc_valid, Some(true));
+        assert!(frame.samples.iter().any(|&sample| sample != 0.0));
+        assert_eq!(decoder.next_frame().unwrap().unwrap().crc_valid, None);
+
+        let mut decoder = Decoder::new(protected_buffer(1));
+        decoder.set_crc_policy(CrcPolicy::Conceal);
+
+        let frame = decoder.next_frame().unwrap().unwrap();
+        assert_eq!(frame.crc_valid, Some(false));
+        assert!(frame.samples.iter().all(|&sample| sample == 0.0));
+    }
+
+    #[test]
+    fn test_crc_log() {
+        let logged = Cell::new(0);
+
+        // Garbage in front of the stream, dropped from the buffer before the frame is decoded.
+        let mut data = vec![0; 40000];
+        data.extend_from_slice(&protected_buffer(1).data);
+
+        let mut decoder = Decoder::incremental();
+        decoder.set_crc_policy(CrcPolicy::Log(Box::new(|pos| logged.set(pos))));
+
+        let mut frames = Vec::new();
+
+        for chunk in data.chunks(5000) {
+            decoder.feed(chunk);
+            frames.extend(decoder.decode_available().unwrap());
+        }
+
+        let frame = frames
+            .iter()
+            .find(|frame| frame.crc_valid.is_some())
+            .unwrap();
+        assert_eq!(frame.crc_valid, Some(false));
+        assert!(frame.samples.iter().any(|&sample| sample != 0.0));
+        assert_eq!(logged.get(), 40417);
+    }
+
+    #[test]
+    fn test_resync_after_corrupt_frame() {
+        let mut buffer =
+            Buffer::create_buffer_from_file("mp3-examples/test_data_100kb.mp3").unwrap();
+
+        // Frames start at bytes 0, 417, 1043, 1774 and 2609. Breaking the sync word of the frame
+        // at 1774 only loses that frame, the one at 1043 is decoded while in sync.
+        buffer.data.to_mut()[1774] = 0;
+
+        // The Xing frame at 0 is skipped without counting as discarded.
+        let mut decoder = Decoder::new(buffer);
+        assert_eq!(decoder.next_frame().unwrap().unwrap().discarded_bytes, 0);
+        assert_eq!(decoder.next_frame().unwrap().unwrap().discarded_bytes, 0);
+        assert_eq!(
+            decoder.next_frame().unwrap().unwrap().discarded_bytes,
+            2609 - 1774
+        );
+        assert_eq!(decoder.discarded_bytes(), 2609 - 1774);
+        assert_eq!(decoder.count(), 153 - 4);
+    }
+
+    #[test]
+    fn test_lsf_stream() {
+        // Ten MPEG-2 frames at 64 kbit/s and 22.05 kHz in joint stereo with M/S and intensity
+        // stereo. The expected samples come from minimp3.
+        let buffer = Buffer::create_buffer_from_file("mp3-examples/test_data_lsf.mp3").unwrap();
+        let frames: Vec<DecodedFrame> = Decoder::new(buffer).collect();
+
+        assert_eq!(frames.len(), 10);
+        assert!(frames.iter().all(|frame| frame.sample_rate == 22050
+            && frame.channels == 2
+            && frame.samples.len() == 576 * 2));
+
+        let samples: Vec<f32> = frames.into_iter().flat_map(|frame| frame.samples).collect();
+
+        for (index, expected) in [
+            (1000, -0.0016997526),
+            (1151, 0.00146455),
+            (5000, 0.016756205),
+            (5001, -0.03534716),
+            (11519, 0.0019393978),
+        ] {
+            assert!((samples[index] - expected).abs() < 1e-5);
+        }
+
+        let energy: f32 = samples.iter().map(|sample| sample * sample).sum();
+        assert!((energy - 2.84424).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_vbr_frame_is_skipped() {
+        let buffer = Buffer::create_buffer_from_file("mp3-examples/test_data_100kb.mp3").unwrap();
+        let mut decoder = Decoder::new(buffer);
+
+        let frames = decoder.vbr_info().unwrap().frames.unwrap();
+        assert_eq!(decoder.by_ref().count(), frames as usize);
+        assert_eq!(decoder.discarded_bytes(), 0);
+    }
+
+    #[test]
+    fn test_gapless() {
+        let buffer = Buffer::create_buffer_from_file("mp3-examples/test_data_100kb.mp3").unwrap();
+        let mut decoder = Decoder::new(buffer);
+
+        // 576 samples encoder delay and 529 decoder delay leave 47 of the first 1152.
+        assert_eq!(decoder.next_frame().unwrap().unwrap().samples.len(), 47 * 2);
+
+        // 153 frames of 1152 samples without the delay and the 679 samples of padding.
+        let samples: usize = decoder.map(|frame| frame.samples.len()).sum::<usize>() + 47 * 2;
+        assert_eq!(samples, (153 * 1152 - 576 - 679) * 2);
+
+        let buffer = Buffer::create_buffer_from_file("mp3-examples/test_data_100kb.mp3").unwrap();
+        let mut decoder = Decoder::new(buffer);
+        decoder.set_encoder_delay(1000, 0);
+
+        assert!(decoder.next_frame().unwrap().unwrap().samples.is_empty());
+        assert_eq!(
+            decoder.next_frame().unwrap().unwrap().samples.len(),
+            775 * 2
+        );
+    }
+
+    #[test]
+    fn test_gapless_itunes() {
+        let with_itunes_comment = |text: &[u8]| {
+            let mut comment = b"\0engiTunSMPB\0".to_vec();
+            comment.extend_from_slice(text);
+
+            let mut data = b"ID3\x03\0\0\0\0\0".to_vec();
+            data.push(comment.len() as u8 + 10);
+            data.extend_from_slice(b"COMM\0\0\0");
+            data.push(comment.len() as u8);
+            data.extend_from_slice(&[0, 0]);
+            data.extend(comment);
+
+            // Without the frame holding the Xing and LAME tags.
+            let stream = fs::read("mp3-examples/test_data_100kb.mp3").unwrap();
+            data.extend_from_slice(&stream[417..]);
+
+            Decoder::new(Buffer::create_buffer_from_bytes(data))
+        };
+
+        // 576 samples encoder delay, 679 samples padding and the length of the encoder input.
+        let decoder = with_itunes_comment(b" 00000000 00000240 000002A7 000000000002AB99");
+        assert!(decoder.vbr_info().is_none());
+        let samples: usize = decoder.map(|frame| frame.samples.len()).sum();
+        assert_eq!(samples, 0x2ab99 * 2);
+
+        // Without the length, the padding is dropped from the end of the complete input.
+        let decoder = with_itunes_comment(b" 00000000 00000240 000002A7");
+        let samples: usize = decoder.map(|frame| frame.samples.len()).sum();
+        assert_eq!(samples, (153 * 1152 - 576 - 679) * 2);
+    }
+
+    #[test]
+    fn test_id3v2_is_skipped() {
+        let mut data = b"ID3\x03\0\0\0\0\0\x2cTIT2\0\0\0\x06\0\0\0Title".to_vec();
+        // A picture holding what looks like two frames of the stream.
+        data.extend_from_slice(b"APIC\0\0\0\x12\0\0\0image/jpeg\0\0\0");
+        data.extend_from_slice(&[0xff, 0xfb, 0x90, 0x64]);
+        data.extend(fs::read("mp3-examples/test_data_100kb.mp3").unwrap());
+
+        let mut decoder = Decoder::new(Buffer::create_buffer_from_bytes(data));
+
+        assert_eq!(decoder.id3v2().unwrap().title.as_deref(), Some("Title"));
+        assert!(decoder.vbr_info().is_some());
+        assert_eq!(decoder.by_ref().count(), 153);
+        assert_eq!(decoder.discarded_bytes(), 0);
+    }
+
+    #[test]
+    fn test_trailing_tags_are_cut_off() {
+        let mut data = fs::read("mp3-examples/test_data_100kb.mp3").unwrap();
+
+        // An APEv2 tag without header and a Lyrics3v2 block in front of the ID3v1 tag.
+        data.extend_from_slice(b"\x05\0\0\0\0\0\0\0Title\0Title");
+        data.extend_from_slice(b"APETAGEX\xd0\x07\0\0\x33\0\0\0\x01\0\0\0");
+        data.extend_from_slice(&[0; 12]);
+        data.extend_from_slice(b"LYRICSBEGININD0000210000021LYRICS200");
+
+        let length = data.len();
+
+        data.extend_from_slice(b"TAG+");
+        data.resize(length + 227, 0);
+        data.extend_from_slice(b"TAGTitle");
+        data.resize(length + 227 + 127, 0);
+        data.push(8);
+
+        let mut decoder = Decoder::new(Buffer::create_buffer_from_bytes(data));
+
+        assert_eq!(decoder.id3v1().unwrap().title, "Title");
+        assert_eq!(decoder.id3v1().unwrap().genre_name(), Some("Jazz"));
+        assert!(decoder.ape().unwrap().get("title").is_some());
+        assert_eq!(decoder.by_ref().count(), 153);
+        assert_eq!(decoder.discarded_bytes(), 0);
+    }
+
+    #[test]
+    fn test_from_reader() {
+        let data = fs::read("mp3-examples/test_data_100kb.mp3").unwrap();
+        let expected: Vec<f32> = Decoder::new(Buffer::create_buffer_from_bytes(data.clone()))
+            .flat_map(|frame| frame.samples)
+            .collect();
+
+        // A reader borrowing the data.
+        let samples: Vec<f32> = Decoder::from_reader(data.as_slice())
+            .unwrap()
+            .flat_map(|frame| frame.samples)
+            .collect();
+        assert!(samples == expected);
+
+        let mut decoder = Decoder::from_reader(ChunkedReader { data, pos: 0 }).unwrap();
+        let mut samples = Vec::new();
+
+        while let Some(frame) = decoder.next_frame().unwrap() {
+            assert!(decoder.buffer.data.len() <= STREAM_LOOKAHEAD + STREAM_CHUNK);
+            samples.extend(frame.samples);
+        }
+
+        assert!(samples == expected);
+        assert_eq!(decoder.discarded_bytes(), 0);
+    }
+
+    #[test]
+    fn test_from_reader_with_tags() {
+        // An ID3v2 tag longer than the first read, with a picture full of sync words.
+        let mut data = b"ID3\x04\0\0\0\x02\x19\x48TIT2\0\0\0\x06\0\0\0Title".to_vec();
+        data.extend_from_slice(b"APIC\0\x02\x19\x2e\0\0\0image/jpeg\0\0\0");
+        data.extend([0xff, 0xfb, 0x90, 0x64].repeat(9000));
+        data.extend(fs::read("mp3-examples/test_data_100kb.mp3").unwrap());
+        data.extend_from_slice(b"TAGTitle");
+        data.resize(data.len() + 120, 0);
+
+        let mut decoder = Decoder::from_reader(ChunkedReader { data, pos: 0 }).unwrap();
+
+        assert_eq!(decoder.id3v2().unwrap().pictures[0].data.len(), 36000);
+        assert!(decoder.vbr_info().is_some());
+        assert!(decoder.id3v1().is_none());
+
+        assert_eq!(decoder.by_ref().count(), 153);
+        assert_eq!(decoder.id3v1().unwrap().title, "Title");
+        assert_eq!(decoder.discarded_bytes(), 0);
+    }
+
+    /// The 100kb sample followed by an APEv2 tag, with a header and a picture full of sync words
+    /// that does not fit in the buffer of a streaming decoder, and an ID3v1 tag.
+    fn data_with_large_ape_tag() -> Vec<u8> {
+        let mut item = 36000u32.to_le_bytes().to_vec();
+        item.extend_from_slice(&2u32.to_le_bytes());
+        item.extend_from_slice(b"Cover Art (Front)\0");
+        item.extend([0xff, 0xfb, 0x90, 0x64].repeat(9000));
+
+        let mut data = fs::read("mp3-examples/test_data_100kb.mp3").unwrap();
+
+        for flags in [0xa000_0000u32, 0x8000_0000] {
+            data.extend_from_slice(b"APETAGEX");
+
+            for field in [2000, item.len() as u32 + 32, 1, flags] {
+                data.extend_from_slice(&field.to_le_bytes());
+            }
+
+            data.extend_from_slice(&[0; 8]);
+
+            if flags & 1 << 29 != 0 {
+                data.extend_from_slice(&item);
+            }
+        }
+
+        data.extend_from_slice(b"TAGTitle");
+        data.resize(data.len() + 120, 0);
+        data
+    }
+
+    #[test]
+    fn test_from_reader_with_large_ape_tag() {
+        let data = data_with_large_ape_tag();
+        assert!(data.len() - 128 - 102400 > STREAM_LOOKAHEAD + STREAM_CHUNK);
+
+        let mut decoder = Decoder::from_reader(ChunkedReader { data, pos: 0 }).unwrap();
+
+        assert_eq!(decoder.by_ref().count(), 153);
+        assert_eq!(decoder.discarded_bytes(), 0);
+        assert_eq!(decoder.id3v1().unwrap().title, "Title");
+
+        let ApeValue::Binary(picture) = &decoder.ape().unwrap().items[0].value else {
+            panic!("Not a binary item");
+        };
+        assert_eq!(picture.len(), 36000);
+    }
+
+    #[test]
+    fn test_incremental_with_large_ape_tag() {
+        let mut decoder = Decoder::incremental();
+        let mut frames = 0;
+
+        for chunk in data_with_large_ape_tag().chunks(777) {
+            decoder.feed(chunk);
+            frames += decoder.decode_available().unwrap().len();
+        }
+
+        decoder.finish();
+        frames += decoder.decode_available().unwrap().len();
+
+        assert_eq!(frames, 153);
+        assert_eq!(decoder.discarded_bytes(), 0);
+        assert_eq!(decoder.id3v1().unwrap().title, "Title");
+        assert_eq!(decoder.ape().unwrap().items.len(), 1);
+    }
+
+    #[test]
+    fn test_incremental() {
+        let data = fs::read("mp3-examples/test_data_100kb.mp3").unwrap();
+        let expected: Vec<f32> = Decoder::new(Buffer::create_buffer_from_bytes(data.clone()))
+            .flat_map(|frame| frame.samples)
+            .collect();
+
+        let mut decoder = Decoder::incremental();
+        let mut samples = Vec::new();
+
+        for chunk in data.chunks(777) {
+            decoder.feed(chunk);
+            samples.extend(
+                decoder
+                    .decode_available()
+                    .unwrap()
+                    .into_iter()
+                    .flat_map(|frame| frame.samples),
+            );
+        }
+
+        decoder.finish();
+        samples.extend(
+            decoder
+                .decode_available()
+                .unwrap()
+                .into_iter()
+                .flat_map(|frame| frame.samples),
+        );
+
+        assert!(samples == expected);
+        assert_eq!(decoder.discarded_bytes(), 0);
+    }
+
+    #[test]
+    fn test_bytes_needed() {
+        let data = fs::read("mp3-examples/test_data_100kb.mp3").unwrap();
+        let mut decoder = Decoder::incremental();
+
+        decoder.feed(&data[..100]);
+        assert!(decoder.decode_available().unwrap().is_empty());
+        assert_eq!(decoder.bytes_needed(), STREAM_LOOKAHEAD - 100);
+
+        let mut pos = 100;
+        let mut frames = 0;
+
+        // Each time the bytes needed complete the next frame and the header after it.
+        while pos + decoder.bytes_needed() <= data.len() {
+            decoder.feed(&data[pos..pos + decoder.bytes_needed()]);
+            pos += decoder.bytes_needed();
+
+            let decoded = decoder.decode_available().unwrap().len();
+            assert!(decoded > 0);
+            frames += decoded;
+        }
+
+        // The last frame is only known to be complete at the end of the stream.
+        decoder.feed(&data[pos..]);
+        assert!(decoder.decode_available().unwrap().is_empty());
+
+        decoder.finish();
+        frames += decoder.decode_available().unwrap().len();
+
+        assert_eq!(frames, 153);
+    }
+
+    #[test]
+    fn test_borrowed_slice() {
+        static DATA: &[u8] = include_bytes!("../mp3-examples/test_data_100kb.mp3");
+
+        let expected: Vec<f32> = Decoder::new(Buffer::create_buffer_from_bytes(DATA.to_vec()))
+            .flat_map(|frame| frame.samples)
+            .collect();
+
+        let mut decoder = Decoder::new(Buffer::create_buffer_from_slice(DATA));
+        let samples: Vec<f32> = decoder.by_ref().flat_map(|frame| frame.samples).collect();
+
+        assert!(samples == expected);
+        assert!(matches!(decoder.buffer.data, Cow::Borrowed(_)));
+    }
+}
@@ -1,3 +1,5 @@
+use std::{error::Error, fmt};
+
 #[derive(Debug)]
 pub enum ErrorType {
     InvalidHeader,
@@ -10,4 +12,38 @@ pub enum ErrorType {
     UnknownMode,
     BigValuesOutOfRange,
     BlockTypeForbidden,
+    InvalidHuffmanCode,
+    InvalidBitAllocation,
+    SyncNotFound,
+    Io(std::io::Error),
+}
+
+impl fmt::Display for ErrorType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidHeader => write!(f, "invalid frame header"),
+            Self::OutOfIndex => write!(f, "read past the end of the data"),
+            Self::Overflow => write!(f, "more than 32 bits read at once"),
+            Self::UnknownLayer => write!(f, "unknown layer"),
+            Self::UnknownVersion => write!(f, "unknown MPEG version"),
+            Self::UnknownBitrate => write!(f, "unknown bitrate"),
+            Self::UnknownFrequency => write!(f, "unknown sampling frequency"),
+            Self::UnknownMode => write!(f, "unknown channel mode"),
+            Self::BigValuesOutOfRange => write!(f, "big_values out of range"),
+            Self::BlockTypeForbidden => write!(f, "forbidden block type"),
+            Self::InvalidHuffmanCode => write!(f, "invalid Huffman code"),
+            Self::InvalidBitAllocation => write!(f, "invalid bit allocation"),
+            Self::SyncNotFound => write!(f, "no frame sync found"),
+            Self::Io(error) => write!(f, "I/O error: {error}"),
+        }
+    }
+}
+
+impl Error for ErrorType {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
 }
@@ -1,4 +1,12 @@
-use crate::{buffer::Buffer, header::Mode, side_info::SideInfo, Header};
+use crate::{
+    buffer::Buffer,
+    constant, crc,
+    error::ErrorType,
+    header::{Layer, Version},
+    huffman, reorder, requantize,
+    side_info::SideInfo,
+    stereo, Header,
+};
 
 const SLEN_TABLE: [[u8; 2]; 16] = [
     [0, 0],
@@ -22,40 +30,57 @@ const SLEN_TABLE: [[u8; 2]; 16] = [
 #[derive(Debug)]
 pub struct Frame {
     pub header: Header,
-    crc: Option<u16>,
+    pub crc: Option<u16>,
     pub side_info: SideInfo,
-    length_byte: usize,
+    pub length_byte: usize,
     pub granules_data: Vec<GranuleData>,
 }
 
 #[derive(Debug)]
 pub struct GranuleData {
-    scale_factor: Vec<u8>,
-    huffman_code: Vec<u8>,
+    pub scale_factor: Vec<u8>,
+    /// Per scalefactor, the intensity position that marks a band as not intensity coded. Only
+    /// filled for the right channel of MPEG-2 intensity stereo, MPEG-1 always uses 7.
+    pub illegal_intensity_position: Vec<u8>,
+    pub huffman_code: [i32; 576],
+    pub spectrum: [f32; 576],
+    pub subband_samples: [f32; 576],
 }
 
 impl GranuleData {
     fn new() -> Self {
         Self {
             scale_factor: Vec::new(),
-            huffman_code: Vec::new(),
+            illegal_intensity_position: Vec::new(),
+            huffman_code: [0; 576],
+            spectrum: [0.0; 576],
+            subband_samples: [0.0; 576],
         }
     }
 }
 
 impl Frame {
-    pub fn create_from_buffer(buffer: &mut Buffer) -> Self {
-        let header = Header::create_from_buffer(buffer);
+    pub fn create_from_buffer(buffer: &mut Buffer) -> Result<Self, ErrorType> {
+        let header = Header::create_from_buffer(buffer)?;
+        header.validate_header()?;
+
+        if header.layer != Layer::Layer3 {
+            return Err(ErrorType::UnknownLayer);
+        }
+
+        Self::create_from_header(buffer, header)
+    }
+
+    /// Reads the rest of a Layer III frame whose header was already read from `buffer`.
+    pub fn create_from_header(buffer: &mut Buffer, header: Header) -> Result<Self, ErrorType> {
         let crc = if header.error_protection {
-            Some(buffer.get_bits(16).unwrap() as u16)
+            Some(buffer.get_bits(16)? as u16)
         } else {
             None
         };
 
-        let side_info = SideInfo::create_from_buffer(buffer, &header.mode).unwrap();
-        let length_byte = 144000
-            * (header.get_bitrate().unwrap() / header.get_frequency().unwrap()) as usize
-            + header.padding_bit as usize;
+        let side_info = SideInfo::create_from_buffer(buffer, &header)?;
+        let length_byte = header.get_frame_length()?;
 
         let mut granules_data: Vec<GranuleData> = Vec::new();
 
@@ -63,123 +88,383 @@ impl Frame {
             granules_data.push(GranuleData::new());
         }
 
-        Self {
+        Ok(Self {
             header,
             crc,
             side_info,
             length_byte,
             granules_data,
-        }
+        })
     }
 
-    fn decode_scalefactors(&mut self, buffer: &mut Buffer) {
-        for (i, granule) in self.side_info.granule_channels.iter().enumerate() {
-            let slen1 = SLEN_TABLE[granule.scalefac_compress as usize][0] as u32;
-            let slen2 = SLEN_TABLE[granule.scalefac_compress as usize][1] as u32;
+    fn decode_scalefactors(
+        &mut self,
+        buffer: &mut Buffer,
+        granule_index: usize,
+        channel_index: usize,
+    ) -> Result<(), ErrorType> {
+        if self.header.version != Version::MPEG1 {
+            return self.decode_scalefactors_lsf(buffer, channel_index);
+        }
 
-            let start_pos = buffer.pos;
+        let channels = self.header.get_channels();
+        let index = granule_index * channels + channel_index;
+        let granule = &self.side_info.granule_channels[index];
 
-            if granule.block_type != 2 {
-                for _ in 0..(18 - (granule.mixed_block_flag as usize)) {
-                    self.granules_data[i]
-                        .scale_factor
-                        .push(buffer.get_bits(slen1).unwrap() as u8);
-                }
+        let slen1 = SLEN_TABLE[granule.scalefac_compress as usize][0] as u32;
+        let slen2 = SLEN_TABLE[granule.scalefac_compress as usize][1] as u32;
+
+        let mut scale_factor: Vec<u8> = Vec::new();
 
-                for _ in 0..18 {
-                    self.granules_data[i]
-                        .scale_factor
-                        .push(buffer.get_bits(slen2).unwrap() as u8);
+        if granule.windows_switching && granule.block_type == 2 {
+            // Long bands 0..8 of a mixed block, then bands 0..12 (or 3..12) for each window.
+            if granule.mixed_block_flag {
+                for _ in 0..8 {
+                    scale_factor.push(buffer.get_bits(slen1)? as u8);
                 }
+            }
+
+            let first_band = if granule.mixed_block_flag { 3 } else { 0 };
+
+            for band_index in first_band..12 {
+                let slen = if band_index < 6 { slen1 } else { slen2 };
 
                 for _ in 0..3 {
-                    self.granules_data[i].scale_factor.push(0);
+                    scale_factor.push(buffer.get_bits(slen)? as u8);
                 }
-            } else {
-                let (is_first_half, channel_index) = if self.header.mode == Mode::SingleChannel {
-                    (i % 2 == 0, 0)
-                } else {
-                    (i / 2 == 0, i % 2)
-                };
-
-                let scfsi = if is_first_half {
-                    0
-                } else if self.header.mode == Mode::SingleChannel {
-                    self.side_info.scfsi
-                } else {
-                    self.side_info.scfsi & (0xf0 >> (4 * (channel_index)))
-                };
-
-                if scfsi & 8 == 8 {
-                    for band_index in 0..6 {
-                        let scale_factor =
-                            self.granules_data[channel_index].scale_factor[band_index];
-                        self.granules_data[i].scale_factor.push(scale_factor);
-                    }
-                } else {
-                    for band_index in 0..6 {
-                        self.granules_data[i]
-                            .scale_factor
-                            .push(buffer.get_bits(slen1).unwrap() as u8);
+            }
+
+            scale_factor.extend_from_slice(&[0, 0, 0]);
+        } else {
+            let scfsi = (self.side_info.scfsi >> (4 * (channels - 1 - channel_index))) & 0xf;
+            let bands: [(usize, usize, u32); 4] = [
+                (0, 6, slen1),
+                (6, 11, slen1),
+                (11, 16, slen2),
+                (16, 21, slen2),
+            ];
+
+            for (group, (start, end, slen)) in bands.into_iter().enumerate() {
+                let reuse = granule_index == 1 && scfsi & (8 >> group) != 0;
+
+                for band_index in start..end {
+                    if reuse {
+                        let previous = &self.granules_data[channel_index].scale_factor;
+                        scale_factor.push(previous.get(band_index).copied().unwrap_or(0));
+                    } else {
+                        scale_factor.push(buffer.get_bits(slen)? as u8);
                     }
                 }
+            }
 
-                if scfsi & 4 == 4 {
-                    for band_index in 6..11 {
-                        let scale_factor =
-                            self.granules_data[channel_index].scale_factor[band_index];
-                        self.granules_data[i].scale_factor.push(scale_factor);
-                    }
-                } else {
-                    for band_index in 6..11 {
-                        self.granules_data[i]
-                            .scale_factor
-                            .push(buffer.get_bits(slen1).unwrap() as u8);
-                    }
+            scale_factor.push(0);
+        }
+
+        self.granules_data[index].scale_factor = scale_factor;
+
+        Ok(())
+    }
+
+    /// MPEG-2 LSF scalefactors: `scalefac_compress` selects up to four slen values and how many
+    /// scalefactors are read with each of them. The right channel of an intensity stereo frame
+    /// uses its own partitioning, where the largest value of each slen marks an illegal
+    /// intensity position. The result uses the same layout as the MPEG-1 scalefactors, with 6
+    /// long bands at the start of a mixed block.
+    fn decode_scalefactors_lsf(
+        &mut self,
+        buffer: &mut Buffer,
+        channel_index: usize,
+    ) -> Result<(), ErrorType> {
+        let is_intensity_channel = self.header.intensity_stereo && channel_index == 1;
+        let granule = &mut self.side_info.granule_channels[channel_index];
+
+        let block_index = match (
+            granule.windows_switching && granule.block_type == 2,
+            granule.mixed_block_flag,
+        ) {
+            (false, _) => 0,
+            (true, false) => 1,
+            (true, true) => 2,
+        };
+
+        let (slen, table_index) = if is_intensity_channel {
+            let compress = granule.scalefac_compress as u32 >> 1;
+
+            match compress {
+                0..=179 => ([compress / 36, compress % 36 / 6, compress % 36 % 6, 0], 3),
+                180..=243 => {
+                    let compress = compress - 180;
+                    (
+                        [(compress % 64) >> 4, (compress % 16) >> 2, compress % 4, 0],
+                        4,
+                    )
                 }
+                _ => {
+                    let compress = compress - 244;
+                    ([compress / 3, compress % 3, 0, 0], 5)
+                }
+            }
+        } else {
+            let compress = granule.scalefac_compress as u32;
+            granule.preflag = compress >= 500;
 
-                if scfsi & 2 == 2 {
-                    for band_index in 11..16 {
-                        let scale_factor =
-                            self.granules_data[channel_index].scale_factor[band_index];
-                        self.granules_data[i].scale_factor.push(scale_factor);
-                    }
-                } else {
-                    for band_index in 11..16 {
-                        self.granules_data[i]
-                            .scale_factor
-                            .push(buffer.get_bits(slen2).unwrap() as u8);
-                    }
+            match compress {
+                0..=399 => (
+                    [
+                        (compress >> 4) / 5,
+                        (compress >> 4) % 5,
+                        (compress % 16) >> 2,
+                        compress % 4,
+                    ],
+                    0,
+                ),
+                400..=499 => {
+                    let compress = compress - 400;
+                    (
+                        [(compress >> 2) / 5, (compress >> 2) % 5, compress % 4, 0],
+                        1,
+                    )
+                }
+                _ => {
+                    let compress = compress - 500;
+                    ([compress / 3, compress % 3, 0, 0], 2)
                 }
+            }
+        };
 
-                if scfsi & 1 == 1 {
-                    for band_index in 16..21 {
-                        let scale_factor =
-                            self.granules_data[channel_index].scale_factor[band_index];
-                        self.granules_data[i].scale_factor.push(scale_factor);
-                    }
-                } else {
-                    for band_index in 16..21 {
-                        self.granules_data[i]
-                            .scale_factor
-                            .push(buffer.get_bits(slen2).unwrap() as u8);
-                    }
+        let counts = &constant::SCALEFACTOR_COUNT_LSF[table_index][block_index];
+        let mut scale_factor: Vec<u8> = Vec::new();
+        let mut illegal_intensity_position: Vec<u8> = Vec::new();
+
+        for (&slen, &count) in slen.iter().zip(counts) {
+            for _ in 0..count {
+                scale_factor.push(buffer.get_bits(slen)? as u8);
+
+                // Scalefactors without any bits are plain position 0, which is never illegal.
+                if is_intensity_channel {
+                    illegal_intensity_position.push(if slen == 0 {
+                        u8::MAX
+                    } else {
+                        ((1 << slen) - 1) as u8
+                    });
                 }
+            }
+        }
+
+        // The last band (or the last band of each short window) has no scalefactor of its own.
+        let padding = if block_index == 0 { 1 } else { 3 };
+        scale_factor.extend(std::iter::repeat_n(0, padding));
+
+        self.granules_data[channel_index].scale_factor = scale_factor;
+        self.granules_data[channel_index].illegal_intensity_position = illegal_intensity_position;
+
+        Ok(())
+    }
 
-                self.granules_data[i].scale_factor.push(0);
+    /// Decodes scalefactors and Huffman codes of every granule/channel from `buffer`, which has
+    /// to hold the main data of this frame (including any bytes taken from the bit reservoir)
+    /// starting at its current position.
+    pub fn decode_main_data(&mut self, buffer: &mut Buffer) -> Result<(), ErrorType> {
+        let channels = self.header.get_channels();
+
+        for granule_index in 0..self.header.get_granules() {
+            for channel_index in 0..channels {
+                let index = granule_index * channels + channel_index;
+                let part_2_start = buffer.pos;
+
+                self.decode_scalefactors(buffer, granule_index, channel_index)?;
+
+                let granule = &self.side_info.granule_channels[index];
+                let part_3_end = part_2_start + granule.part_23_length as usize;
+
+                self.granules_data[index].huffman_code = huffman::decode_huffman(
+                    buffer,
+                    granule,
+                    self.header.get_sample_rate_index(),
+                    part_3_end,
+                )?;
+
+                buffer.set_pos(part_3_end);
             }
         }
+
+        Ok(())
     }
 
-    pub fn decode_main_data(&mut self, buffer: &mut Buffer) {
-        if self.side_info.main_data_begin != 0 {
-            buffer.set_pos(self.header.pos - self.side_info.main_data_begin as usize);
+    pub fn requantize(&mut self) {
+        for (granule, data) in self
+            .side_info
+            .granule_channels
+            .iter()
+            .zip(self.granules_data.iter_mut())
+        {
+            data.spectrum = requantize::requantize(
+                granule,
+                &data.scale_factor,
+                &data.huffman_code,
+                self.header.get_sample_rate_index(),
+            );
+        }
+    }
+
+    pub fn process_stereo(&mut self) {
+        if self.header.get_channels() != 2 {
+            return;
+        }
+
+        for granule_index in 0..self.header.get_granules() {
+            let (left, right) = self.granules_data[granule_index * 2..].split_at_mut(1);
+
+            stereo::process_stereo(
+                &self.header,
+                &self.side_info.granule_channels[granule_index * 2 + 1],
+                &right[0].scale_factor,
+                &right[0].illegal_intensity_position,
+                &mut left[0].spectrum,
+                &mut right[0].spectrum,
+            );
+        }
+    }
+
+    pub fn reorder_and_reduce_aliases(&mut self) {
+        for (granule, data) in self
+            .side_info
+            .granule_channels
+            .iter()
+            .zip(self.granules_data.iter_mut())
+        {
+            let sample_rate_index = self.header.get_sample_rate_index();
+
+            reorder::reorder(granule, sample_rate_index, &mut data.spectrum);
+            reorder::reduce_aliases(granule, sample_rate_index, &mut data.spectrum);
+        }
+    }
+
+    /// Checks the CRC of an error protected frame against the last two header bytes and the side
+    /// info in `buffer`. Returns `None` when the frame carries no CRC.
+    pub fn check_crc(&self, buffer: &Buffer) -> Option<bool> {
+        let crc = self.crc?;
+        let start = self.header.pos / 8;
+        let side_info_bytes = self.header.get_side_info_length();
+
+        // The two sync/version/layer bytes and the CRC itself are not covered.
+        let mut data = buffer.data.get(start + 2..start + 4)?.to_vec();
+        data.extend_from_slice(buffer.data.get(start + 6..start + 6 + side_info_bytes)?);
+
+        Some(crc::crc16(&data, data.len() * 8) == crc)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// `(value, bits)` pairs packed one after another.
+    type Fields = Vec<(u32, u32)>;
+
+    /// Decodes an MPEG-2 Layer III frame at 64 kbit/s and 22.05 kHz with one granule per
+    /// `(scalefac_compress, short_block, scalefactors)` channel, whose main data only holds the
+    /// scalefactors.
+    fn lsf_frame(mode: u8, channels: &[(u32, bool, Fields)]) -> Frame {
+        let mut fields = vec![(0, 8), (0, if channels.len() == 1 { 1 } else { 2 })];
+
+        for (scalefac_compress, short_block, scalefactors) in channels {
+            let part_23_length = scalefactors.iter().map(|&(_, bits)| bits).sum();
+
+            fields.extend([
+                (part_23_length, 12),
+                (0, 9),
+                (150, 8),
+                (*scalefac_compress, 9),
+            ]);
+
+            if *short_block {
+                fields.extend([
+                    (1, 1),
+                    (2, 2),
+                    (0, 1),
+                    (0, 5),
+                    (0, 5),
+                    (0, 3),
+                    (0, 3),
+                    (0, 3),
+                ]);
+            } else {
+                fields.extend([(0, 1), (0, 5), (0, 5), (0, 5), (0, 4), (0, 3)]);
+            }
+
+            fields.extend([(0, 1), (0, 1)]);
         }
 
-        self.decode_scalefactors(buffer);
+        for (_, _, scalefactors) in channels {
+            fields.extend(scalefactors);
+        }
+
+        let mut buffer = Buffer::create_buffer_from_fields(&[0xff, 0xf3, 0x80, mode], &fields, 208);
+        let mut frame = Frame::create_from_buffer(&mut buffer).unwrap();
+
+        frame.decode_main_data(&mut buffer).unwrap();
+        frame
     }
 
-    fn check_crc(&self) {
-        todo!("Implement CRC check for");
+    /// `count` scalefactors of `slen` bits counting up from `first`.
+    fn scalefactors(first: u32, count: u32, slen: u32) -> Fields {
+        (first..first + count)
+            .map(|value| (value % (1 << slen), slen))
+            .collect()
+    }
+
+    #[test]
+    fn test_lsf_scalefactors_with_preflag() {
+        // 500 + 3 * 3 + 2: slen 3 and 2 for 11 and 10 long bands, with preflag.
+        let mut fields = scalefactors(0, 11, 3);
+        fields.extend(scalefactors(11, 10, 2));
+
+        let frame = lsf_frame(0xc0, &[(511, false, fields.clone())]);
+        let data = &frame.granules_data[0];
+
+        let mut expected: Vec<u8> = fields.iter().map(|&(value, _)| value as u8).collect();
+        expected.push(0);
+
+        assert!(frame.side_info.granule_channels[0].preflag);
+        assert_eq!(data.scale_factor, expected);
+        assert!(data.illegal_intensity_position.is_empty());
+    }
+
+    #[test]
+    fn test_lsf_scalefactors_short_block() {
+        // (7 << 4) + (3 << 2) + 0: slen 1, 2, 3 and 0 for 9 short bands each, without preflag.
+        let mut fields = scalefactors(0, 9, 1);
+        fields.extend(scalefactors(9, 9, 2));
+        fields.extend(scalefactors(18, 9, 3));
+
+        let frame = lsf_frame(0xc0, &[(124, true, fields.clone())]);
+
+        let mut expected: Vec<u8> = fields.iter().map(|&(value, _)| value as u8).collect();
+        expected.extend([0; 9 + 3]);
+
+        assert!(!frame.side_info.granule_channels[0].preflag);
+        assert_eq!(frame.granules_data[0].scale_factor, expected);
+    }
+
+    #[test]
+    fn test_lsf_intensity_scalefactors() {
+        // The right channel of intensity stereo: (36 + 2 * 6) << 1 with intensity_scale gives
+        // slen 1, 2 and 0 for 7 long bands each.
+        let mut fields = scalefactors(0, 7, 1);
+        fields.extend(scalefactors(7, 7, 2));
+
+        let frame = lsf_frame(0x50, &[(0, false, Vec::new()), (97, false, fields.clone())]);
+        let data = &frame.granules_data[1];
+
+        let mut expected: Vec<u8> = fields.iter().map(|&(value, _)| value as u8).collect();
+        expected.extend([0; 7 + 1]);
+
+        assert_eq!(frame.granules_data[0].scale_factor, [0; 22]);
+        assert_eq!(data.scale_factor, expected);
+        // The largest value of each slen, and none for scalefactors without bits.
+        assert_eq!(data.illegal_intensity_position[..7], [1; 7]);
+        assert_eq!(data.illegal_intensity_position[7..14], [3; 7]);
+        assert_eq!(data.illegal_intensity_position[14..], [u8::MAX; 7]);
     }
 }
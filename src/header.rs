@@ -8,6 +8,7 @@ use crate::error;
 pub enum Version {
     MPEG1,
     MPEG2,
+    MPEG2_5,
 }
 
 #[derive(PartialEq, Debug)]
@@ -72,6 +73,7 @@ impl Display for Version {
         let version = match self {
             Self::MPEG1 => "MPEG-1",
             Self::MPEG2 => "MPEG-2",
+            Self::MPEG2_5 => "MPEG-2.5",
         };
 
         write!(f, "{version}")
@@ -81,8 +83,9 @@ impl Display for Version {
 impl Version {
     fn decode_version(version: u8) -> Result<Version, error::ErrorType> {
         match version {
-            0 => Ok(Version::MPEG2),
-            1 => Ok(Version::MPEG1),
+            0 => Ok(Version::MPEG2_5),
+            2 => Ok(Version::MPEG2),
+            3 => Ok(Version::MPEG1),
             _ => Err(error::ErrorType::UnknownVersion),
         }
     }
@@ -114,37 +117,48 @@ impl Mode {
 }
 
 impl Header {
-    fn validate_header(&self) -> Result<(), error::ErrorType> {
-        if self.sync_word == 0xfff && self.layer == Layer::Layer3 {
+    pub fn validate_header(&self) -> Result<(), error::ErrorType> {
+        // MPEG-2.5 takes the last bit of the 12 bit sync word as part of its version, and only
+        // defines Layer III.
+        if self.sync_word & 0xffe == 0xffe
+            && (self.version != Version::MPEG2_5 || self.layer == Layer::Layer3)
+            && self.bitrate != 0
+            && self.bitrate != 0xf
+            && self.frequency != 3
+        {
             return Ok(());
         }
 
         Err(error::ErrorType::InvalidHeader)
     }
 
-    pub fn create_from_buffer(buffer: &mut Buffer) -> Self {
+    pub fn create_from_buffer(buffer: &mut Buffer) -> Result<Self, error::ErrorType> {
         let pos = buffer.pos;
         let index = buffer.pos / 8;
 
+        if index + 4 > buffer.data.len() {
+            return Err(error::ErrorType::OutOfIndex);
+        }
+
         let sync_word =
             ((buffer.data[index] as u16) << 4) | (buffer.data[1 + index] as u16 & 0xf0) >> 4;
-        let version = Version::decode_version((buffer.data[1 + index] & 8) >> 3).unwrap();
-        let layer = Layer::decode_layer((buffer.data[1 + index] & 0b110) >> 1).unwrap();
+        let version = Version::decode_version((buffer.data[1 + index] & 0x18) >> 3)?;
+        let layer = Layer::decode_layer((buffer.data[1 + index] & 0b110) >> 1)?;
         let error_protection = (buffer.data[1 + index] & 1) == 0;
         let bitrate = (buffer.data[2 + index] & 0xf0) >> 4;
         let frequency = (buffer.data[2 + index] & 0xc) >> 2;
-        let padding_bit = ((buffer.data[2 + index] & 0x10) >> 1) == 1;
+        let padding_bit = ((buffer.data[2 + index] & 0b10) >> 1) == 1;
         let private_bit = buffer.data[2 + index] & 1 == 1;
-        let mode = Mode::decode_mode((buffer.data[3 + index] & 0xc0) >> 6).unwrap();
-        let intensity_stereo = (buffer.data[3 + index] & 0x20) >> 5 == 1;
-        let ms_stereo = (buffer.data[3 + index] & 0x10) >> 4 == 1;
+        let mode = Mode::decode_mode((buffer.data[3 + index] & 0xc0) >> 6)?;
+        let intensity_stereo = (buffer.data[3 + index] & 0x10) >> 4 == 1;
+        let ms_stereo = (buffer.data[3 + index] & 0x20) >> 5 == 1;
         let copy_right = (buffer.data[3 + index] & 0b1000) >> 3 == 1;
         let copy_of_original = (buffer.data[3 + index] & 0b100) >> 2 == 0;
         let emphasis = buffer.data[3 + index] & 0b11;
 
         buffer.move_pos(32);
 
-        Self {
+        Ok(Self {
             sync_word,
             version,
             layer,
@@ -160,23 +174,115 @@ impl Header {
             copy_of_original,
             emphasis,
             pos,
-        }
+        })
     }
 
     pub fn get_bitrate(&self) -> Result<u16, error::ErrorType> {
-        if self.version == Version::MPEG1 && self.layer == Layer::Layer3 {
-            return Ok(constant::HALF_BITRATE_MPEG1_LAYER3[self.bitrate as usize] as u16 * 2);
+        if self.bitrate > 14 {
+            return Err(error::ErrorType::UnknownBitrate);
         }
 
-        Err(error::ErrorType::UnknownBitrate)
+        let index = self.bitrate as usize;
+
+        Ok(match (&self.version, &self.layer) {
+            (Version::MPEG1, Layer::Layer1) => constant::BITRATE_MPEG1_LAYER1[index],
+            (_, Layer::Layer1) => constant::BITRATE_MPEG2_LAYER1[index],
+            (Version::MPEG1, Layer::Layer2) => constant::BITRATE_MPEG1_LAYER2[index],
+            (Version::MPEG1, _) => constant::HALF_BITRATE_MPEG1_LAYER3[index] as u16 * 2,
+            _ => constant::BITRATE_MPEG2_LAYER2_3[index] as u16,
+        })
+    }
+
+    /// Length of the frame in bytes, including the header and the padding slot.
+    pub fn get_frame_length(&self) -> Result<usize, error::ErrorType> {
+        // Layer I counts in slots of 4 bytes, which also makes its padding slot 4 bytes long.
+        if self.layer == Layer::Layer1 {
+            let slots = 12 * 1000 * self.get_bitrate()? as usize / self.get_frequency()? as usize;
+
+            return Ok((slots + self.padding_bit as usize) * 4);
+        }
+
+        let samples_per_byte = self.get_frame_samples() / 8;
+
+        Ok(
+            samples_per_byte * 1000 * self.get_bitrate()? as usize / self.get_frequency()? as usize
+                + self.padding_bit as usize,
+        )
     }
 
     pub fn get_frequency(&self) -> Result<u16, error::ErrorType> {
+        if self.frequency > 2 {
+            return Err(error::ErrorType::UnknownFrequency);
+        }
+
+        let frequencies = match self.version {
+            Version::MPEG1 => &constant::FREQUENCY_MPEG1,
+            Version::MPEG2 => &constant::FREQUENCY_MPEG2,
+            Version::MPEG2_5 => &constant::FREQUENCY_MPEG2_5,
+        };
+
+        Ok(frequencies[self.frequency as usize])
+    }
+
+    /// Index into the scalefactor band tables: 0-2 for MPEG-1, 3-5 for MPEG-2 and 6-8 for
+    /// MPEG-2.5.
+    pub fn get_sample_rate_index(&self) -> usize {
+        let offset = match self.version {
+            Version::MPEG1 => 0,
+            Version::MPEG2 => 3,
+            Version::MPEG2_5 => 6,
+        };
+
+        offset + self.frequency as usize
+    }
+
+    /// MPEG-1 frames hold two granules, the low sampling frequencies of MPEG-2 and 2.5 only one.
+    pub fn get_granules(&self) -> usize {
         if self.version == Version::MPEG1 {
-            return Ok(constant::FREQUENCY_MPEG1[self.frequency as usize]);
+            2
+        } else {
+            1
         }
+    }
 
-        Err(error::ErrorType::UnknownFrequency)
+    pub fn get_channels(&self) -> usize {
+        if self.mode == Mode::SingleChannel {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Samples per channel in one frame. Only Layer III halves them at the MPEG-2 frequencies.
+    pub fn get_frame_samples(&self) -> usize {
+        match self.layer {
+            Layer::Layer1 => constant::FRAME_SAMPLES_LAYER1 as usize,
+            Layer::Layer2 => constant::FRAME_SMAPLES as usize,
+            Layer::Layer3 => constant::FRAME_SMAPLES as usize / 2 * self.get_granules(),
+        }
+    }
+
+    /// First subband that Layer I and II joint stereo codes in intensity stereo, set to 4, 8, 12
+    /// or 16 by the mode extension. 32 for the other modes.
+    pub fn get_intensity_bound(&self) -> usize {
+        if self.mode != Mode::JointStereo {
+            return 32;
+        }
+
+        ((self.ms_stereo as usize) << 1 | self.intensity_stereo as usize) * 4 + 4
+    }
+
+    /// Length of the side info in bytes.
+    pub fn get_side_info_length(&self) -> usize {
+        match (
+            self.version == Version::MPEG1,
+            self.mode == Mode::SingleChannel,
+        ) {
+            (true, true) => 17,
+            (true, false) => 32,
+            (false, true) => 9,
+            (false, false) => 17,
+        }
     }
 }
 
@@ -188,8 +294,8 @@ impl Display for Header {
     Version: {}
     Layer: {}
     Error Protection: {}
-    Bitrate: {}kb/sec
-    Frequency: {}Hz
+    Bitrate: {}
+    Frequency: {}
     Padding: {}
     Set Private Bit: {}
     Channel Mode: {}
@@ -203,8 +309,15 @@ impl Display for Header {
             self.version,
             self.layer,
             self.error_protection,
-            self.get_bitrate().unwrap(),
-            self.get_frequency().unwrap(),
+            // Unvalidated headers show the index that has no value.
+            match self.get_bitrate() {
+                Ok(bitrate) => format!("{bitrate}kb/sec"),
+                Err(_) => format!("invalid index {}", self.bitrate),
+            },
+            match self.get_frequency() {
+                Ok(frequency) => format!("{frequency}Hz"),
+                Err(_) => format!("invalid index {}", self.frequency),
+            },
             self.padding_bit,
             self.private_bit,
             self.mode,
@@ -227,3 +340,55 @@ impl Display for Header {
         )
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_lsf_header() {
+        // MPEG-2.5, 32 kbit/s, 8 kHz, mono.
+        let mut buffer = Buffer::create_buffer_from_bytes(vec![0xff, 0xe3, 0x48, 0xc0]);
+        let header = Header::create_from_buffer(&mut buffer).unwrap();
+
+        assert!(header.validate_header().is_ok());
+        assert_eq!(header.version, Version::MPEG2_5);
+        assert_eq!(header.get_frequency().unwrap(), 8000);
+        assert_eq!(header.get_sample_rate_index(), 8);
+        assert_eq!(header.get_granules(), 1);
+        assert_eq!(header.get_frame_samples(), 576);
+        assert_eq!(header.get_frame_length().unwrap(), 288);
+        assert_eq!(header.get_side_info_length(), 9);
+    }
+
+    #[test]
+    fn test_lsf_header_without_sync_word() {
+        // The version bits of MPEG-2.5 after a broken sync word.
+        let mut buffer = Buffer::create_buffer_from_bytes(vec![0x12, 0x03, 0x48, 0xc0]);
+        let header = Header::create_from_buffer(&mut buffer).unwrap();
+
+        assert_eq!(header.version, Version::MPEG2_5);
+        assert!(header.validate_header().is_err());
+    }
+
+    #[test]
+    fn test_mpeg2_5_layer1_header() {
+        // MPEG-2.5 version bits with Layer I, which could make frames of 2880 bytes.
+        let mut buffer = Buffer::create_buffer_from_bytes(vec![0xff, 0xe7, 0xe0, 0xc0]);
+        let header = Header::create_from_buffer(&mut buffer).unwrap();
+
+        assert_eq!(header.version, Version::MPEG2_5);
+        assert_eq!(header.layer, Layer::Layer1);
+        assert!(header.validate_header().is_err());
+    }
+
+    #[test]
+    fn test_display_invalid_header() {
+        let mut buffer = Buffer::create_buffer_from_bytes(vec![0xff, 0xfb, 0xfc, 0xc0]);
+        let header = Header::create_from_buffer(&mut buffer).unwrap();
+        let text = header.to_string();
+
+        assert!(text.contains("Bitrate: invalid index 15"));
+        assert!(text.contains("Frequency: invalid index 3"));
+    }
+}
@@ -0,0 +1,907 @@
+use crate::buffer::Buffer;
+
+/// Length of the ID3v2 header and of the footer that may follow the tag.
+const HEADER_LENGTH: usize = 10;
+
+const FLAG_UNSYNCHRONISATION: u8 = 0x80;
+/// Set on ID3v2.2 tags whose frames are compressed with a scheme the standard never defined.
+const FLAG_COMPRESSION: u8 = 0x40;
+const FLAG_EXTENDED_HEADER: u8 = 0x40;
+const FLAG_FOOTER: u8 = 0x10;
+
+/// Length of the ID3v1 tag at the end of the file.
+const ID3V1_LENGTH: usize = 128;
+/// Length of the Enhanced tag that may precede the ID3v1 tag.
+const ID3V1_ENHANCED_LENGTH: usize = 227;
+
+/// Genre names of ID3v1: 0 to 79 from the standard, the rest added by Winamp.
+const GENRES: [&str; 192] = [
+    "Blues",
+    "Classic Rock",
+    "Country",
+    "Dance",
+    "Disco",
+    "Funk",
+    "Grunge",
+    "Hip-Hop",
+    "Jazz",
+    "Metal",
+    "New Age",
+    "Oldies",
+    "Other",
+    "Pop",
+    "R&B",
+    "Rap",
+    "Reggae",
+    "Rock",
+    "Techno",
+    "Industrial",
+    "Alternative",
+    "Ska",
+    "Death Metal",
+    "Pranks",
+    "Soundtrack",
+    "Euro-Techno",
+    "Ambient",
+    "Trip-Hop",
+    "Vocal",
+    "Jazz+Funk",
+    "Fusion",
+    "Trance",
+    "Classical",
+    "Instrumental",
+    "Acid",
+    "House",
+    "Game",
+    "Sound Clip",
+    "Gospel",
+    "Noise",
+    "AlternRock",
+    "Bass",
+    "Soul",
+    "Punk",
+    "Space",
+    "Meditative",
+    "Instrumental Pop",
+    "Instrumental Rock",
+    "Ethnic",
+    "Gothic",
+    "Darkwave",
+    "Techno-Industrial",
+    "Electronic",
+    "Pop-Folk",
+    "Eurodance",
+    "Dream",
+    "Southern Rock",
+    "Comedy",
+    "Cult",
+    "Gangsta",
+    "Top 40",
+    "Christian Rap",
+    "Pop/Funk",
+    "Jungle",
+    "Native American",
+    "Cabaret",
+    "New Wave",
+    "Psychadelic",
+    "Rave",
+    "Showtunes",
+    "Trailer",
+    "Lo-Fi",
+    "Tribal",
+    "Acid Punk",
+    "Acid Jazz",
+    "Polka",
+    "Retro",
+    "Musical",
+    "Rock & Roll",
+    "Hard Rock",
+    "Folk",
+    "Folk-Rock",
+    "National Folk",
+    "Swing",
+    "Fast Fusion",
+    "Bebob",
+    "Latin",
+    "Revival",
+    "Celtic",
+    "Bluegrass",
+    "Avantgarde",
+    "Gothic Rock",
+    "Progressive Rock",
+    "Psychedelic Rock",
+    "Symphonic Rock",
+    "Slow Rock",
+    "Big Band",
+    "Chorus",
+    "Easy Listening",
+    "Acoustic",
+    "Humour",
+    "Speech",
+    "Chanson",
+    "Opera",
+    "Chamber Music",
+    "Sonata",
+    "Symphony",
+    "Booty Bass",
+    "Primus",
+    "Porn Groove",
+    "Satire",
+    "Slow Jam",
+    "Club",
+    "Tango",
+    "Samba",
+    "Folklore",
+    "Ballad",
+    "Power Ballad",
+    "Rhythmic Soul",
+    "Freestyle",
+    "Duet",
+    "Punk Rock",
+    "Drum Solo",
+    "A capella",
+    "Euro-House",
+    "Dance Hall",
+    "Goa",
+    "Drum & Bass",
+    "Club-House",
+    "Hardcore",
+    "Terror",
+    "Indie",
+    "BritPop",
+    "Afro-Punk",
+    "Polsk Punk",
+    "Beat",
+    "Christian Gangsta Rap",
+    "Heavy Metal",
+    "Black Metal",
+    "Crossover",
+    "Contemporary Christian",
+    "Christian Rock",
+    "Merengue",
+    "Salsa",
+    "Thrash Metal",
+    "Anime",
+    "JPop",
+    "Synthpop",
+    "Abstract",
+    "Art Rock",
+    "Baroque",
+    "Bhangra",
+    "Big Beat",
+    "Breakbeat",
+    "Chillout",
+    "Downtempo",
+    "Dub",
+    "EBM",
+    "Eclectic",
+    "Electro",
+    "Electroclash",
+    "Emo",
+    "Experimental",
+    "Garage",
+    "Global",
+    "IDM",
+    "Illbient",
+    "Industro-Goth",
+    "Jam Band",
+    "Krautrock",
+    "Leftfield",
+    "Lounge",
+    "Math Rock",
+    "New Romantic",
+    "Nu-Breakz",
+    "Post-Punk",
+    "Post-Rock",
+    "Psytrance",
+    "Shoegaze",
+    "Space Rock",
+    "Trop Rock",
+    "World Music",
+    "Neoclassical",
+    "Audiobook",
+    "Audio Theatre",
+    "Neue Deutsche Welle",
+    "Podcast",
+    "Indie Rock",
+    "G-Funk",
+    "Dubstep",
+    "Garage Rock",
+    "Psybient",
+];
+
+/// An attached picture, from an `APIC` (or ID3v2.2 `PIC`) frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Picture {
+    pub mime_type: String,
+    /// 3 for the front cover, 4 for the back cover. The other values are listed in the ID3v2
+    /// standard.
+    pub picture_type: u8,
+    pub description: String,
+    pub data: Vec<u8>,
+}
+
+/// A comment, from a `COMM` (or ID3v2.2 `COM`) frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    /// ISO 639-2 language code, such as `eng`.
+    pub language: String,
+    pub description: String,
+    pub text: String,
+}
+
+/// A user defined text, from a `TXXX` (or ID3v2.2 `TXX`) frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserText {
+    pub description: String,
+    pub value: String,
+}
+
+/// The common frames of an ID3v2.2, 2.3 or 2.4 tag, which is stored in front of the first
+/// audio frame.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Id3v2Tag {
+    /// Major version, 2 to 4.
+    pub version: u8,
+    pub revision: u8,
+    /// Length of the whole tag in bytes, including the header and the footer.
+    pub length: usize,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    /// Track number, optionally followed by `/` and the number of tracks.
+    pub track: Option<String>,
+    /// Year, or the recording time of ID3v2.4 such as `2001-09-11`.
+    pub year: Option<String>,
+    pub pictures: Vec<Picture>,
+    pub comments: Vec<Comment>,
+    pub user_texts: Vec<UserText>,
+}
+
+/// Integer stored 7 bits per byte so that it never contains a sync word.
+fn synchsafe(data: &[u8]) -> usize {
+    data.iter()
+        .fold(0, |value, &byte| value << 7 | (byte & 0x7f) as usize)
+}
+
+fn read_uint(data: &[u8]) -> usize {
+    data.iter()
+        .fold(0, |value, &byte| value << 8 | byte as usize)
+}
+
+/// Undoes the unsynchronisation scheme, which inserts a zero byte after every 0xff.
+fn remove_unsynchronisation(data: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(data.len());
+
+    for (index, &byte) in data.iter().enumerate() {
+        if byte == 0 && index > 0 && data[index - 1] == 0xff {
+            continue;
+        }
+
+        result.push(byte);
+    }
+
+    result
+}
+
+/// Splits `data` at the first terminator of a string in `encoding`, two zero bytes for UTF-16
+/// and one otherwise. Without a terminator, all of `data` is the string.
+fn split_terminated(encoding: u8, data: &[u8]) -> (&[u8], &[u8]) {
+    let terminator = if matches!(encoding, 1 | 2) {
+        (0..data.len() / 2)
+            .map(|index| index * 2)
+            .find(|&index| data[index] == 0 && data[index + 1] == 0)
+            .map(|index| (index, index + 2))
+    } else {
+        data.iter()
+            .position(|&byte| byte == 0)
+            .map(|index| (index, index + 1))
+    };
+
+    match terminator {
+        Some((end, next)) => (&data[..end], &data[next..]),
+        None => (data, &[]),
+    }
+}
+
+/// Decodes a string in one of the text encodings: 0 for ISO-8859-1, 1 for UTF-16 with a byte
+/// order mark, 2 for UTF-16BE and 3 for UTF-8.
+fn decode_text(encoding: u8, data: &[u8]) -> String {
+    let text = match encoding {
+        0 => data.iter().map(|&byte| byte as char).collect(),
+        1 | 2 => {
+            let (little_endian, data) = match data {
+                [0xff, 0xfe, rest @ ..] => (true, rest),
+                [0xfe, 0xff, rest @ ..] => (false, rest),
+                _ => (false, data),
+            };
+
+            let units: Vec<u16> = data
+                .chunks_exact(2)
+                .map(|unit| {
+                    if little_endian {
+                        u16::from_le_bytes([unit[0], unit[1]])
+                    } else {
+                        u16::from_be_bytes([unit[0], unit[1]])
+                    }
+                })
+                .collect();
+
+            String::from_utf16_lossy(&units)
+        }
+        _ => String::from_utf8_lossy(data).into_owned(),
+    };
+
+    text.trim_end_matches('\0').to_string()
+}
+
+impl Id3v2Tag {
+    /// Reads the tag starting at the (byte aligned) position of `buffer`, leaving the position
+    /// untouched.
+    pub fn create_from_buffer(buffer: &Buffer) -> Option<Self> {
+        Self::create_from_bytes(buffer.data.get(buffer.pos / 8..)?)
+    }
+
+    /// Length of the whole tag from its header at the start of `data`, which is all that has to
+    /// be read to know how many bytes to skip.
+    pub fn length_from_header(data: &[u8]) -> Option<usize> {
+        let header = data.get(..HEADER_LENGTH)?;
+
+        if !header.starts_with(b"ID3")
+            || !(2..=4).contains(&header[3])
+            || header[6..].iter().any(|&byte| byte & 0x80 != 0)
+        {
+            return None;
+        }
+
+        let has_footer = header[3] == 4 && header[5] & FLAG_FOOTER != 0;
+
+        Some(HEADER_LENGTH + synchsafe(&header[6..]) + if has_footer { HEADER_LENGTH } else { 0 })
+    }
+
+    /// Reads the tag at the start of `data`.
+    pub fn create_from_bytes(data: &[u8]) -> Option<Self> {
+        let length = Self::length_from_header(data)?;
+        let version = data[3];
+        let flags = data[5];
+        let size = synchsafe(&data[6..HEADER_LENGTH]);
+
+        let mut tag = Self {
+            version,
+            revision: data[4],
+            length,
+            ..Default::default()
+        };
+
+        let body = data.get(HEADER_LENGTH..HEADER_LENGTH + size)?;
+        let unsynchronised = flags & FLAG_UNSYNCHRONISATION != 0;
+
+        // ID3v2.4 applies the unsynchronisation to each frame instead of the whole tag.
+        let body = if unsynchronised && version < 4 {
+            remove_unsynchronisation(body)
+        } else {
+            body.to_vec()
+        };
+
+        if version == 2 && flags & FLAG_COMPRESSION != 0 {
+            return Some(tag);
+        }
+
+        let frames_start = match (version, flags & FLAG_EXTENDED_HEADER != 0) {
+            (3, true) => 4 + read_uint(body.get(..4)?),
+            (4, true) => synchsafe(body.get(..4)?),
+            _ => 0,
+        };
+
+        tag.read_frames(body.get(frames_start..)?, unsynchronised);
+
+        Some(tag)
+    }
+
+    fn read_frames(&mut self, data: &[u8], unsynchronised: bool) {
+        let (id_length, header_length) = if self.version == 2 { (3, 6) } else { (4, 10) };
+        let mut pos = 0;
+
+        while pos + header_length <= data.len() {
+            let frame_header = &data[pos..pos + header_length];
+            let id = &frame_header[..id_length];
+
+            // Padding fills the rest of the tag with zeros.
+            if !id
+                .iter()
+                .all(|byte| byte.is_ascii_uppercase() || byte.is_ascii_digit())
+            {
+                break;
+            }
+
+            let size = match self.version {
+                2 => read_uint(&frame_header[3..6]),
+                3 => read_uint(&frame_header[4..8]),
+                _ => synchsafe(&frame_header[4..8]),
+            };
+
+            let start = pos + header_length;
+            let Some(frame_data) = data.get(start..start + size) else {
+                break;
+            };
+
+            pos = start + size;
+
+            let format_flags = if self.version == 2 {
+                0
+            } else {
+                frame_header[9]
+            };
+
+            if let Some(frame_data) = self.frame_content(frame_data, format_flags, unsynchronised) {
+                self.read_frame(id, &frame_data);
+            }
+        }
+    }
+
+    /// Strips the extra bytes announced by the format flags of a frame. `None` for compressed
+    /// or encrypted frames.
+    fn frame_content(&self, data: &[u8], flags: u8, unsynchronised: bool) -> Option<Vec<u8>> {
+        match self.version {
+            3 => {
+                if flags & 0xc0 != 0 {
+                    return None;
+                }
+
+                let grouping = if flags & 0x20 != 0 { 1 } else { 0 };
+
+                Some(data.get(grouping..)?.to_vec())
+            }
+            4 => {
+                if flags & 0x0c != 0 {
+                    return None;
+                }
+
+                let grouping = if flags & 0x40 != 0 { 1 } else { 0 };
+                let data_length = if flags & 0x01 != 0 { 4 } else { 0 };
+                let data = data.get(grouping + data_length..)?;
+
+                if unsynchronised || flags & 0x02 != 0 {
+                    Some(remove_unsynchronisation(data))
+                } else {
+                    Some(data.to_vec())
+                }
+            }
+            _ => Some(data.to_vec()),
+        }
+    }
+
+    fn read_frame(&mut self, id: &[u8], data: &[u8]) {
+        let Some((&encoding, content)) = data.split_first() else {
+            return;
+        };
+
+        match id {
+            b"TIT2" | b"TT2" => self.title = Some(decode_text(encoding, content)),
+            b"TPE1" | b"TP1" => self.artist = Some(decode_text(encoding, content)),
+            b"TALB" | b"TAL" => self.album = Some(decode_text(encoding, content)),
+            b"TRCK" | b"TRK" => self.track = Some(decode_text(encoding, content)),
+            b"TYER" | b"TYE" | b"TDRC" => self.year = Some(decode_text(encoding, content)),
+            b"APIC" | b"PIC" => {
+                // ID3v2.2 gives a three letter image format instead of a MIME type.
+                let (mime_type, rest) = if id == b"PIC" {
+                    let Some((format, rest)) = content.split_at_checked(3) else {
+                        return;
+                    };
+
+                    let mime_type = match format {
+                        b"JPG" => "image/jpeg".to_string(),
+                        b"PNG" => "image/png".to_string(),
+                        _ => decode_text(0, format),
+                    };
+
+                    (mime_type, rest)
+                } else {
+                    let (mime_type, rest) = split_terminated(0, content);
+
+                    (decode_text(0, mime_type), rest)
+                };
+
+                let Some((&picture_type, rest)) = rest.split_first() else {
+                    return;
+                };
+
+                let (description, data) = split_terminated(encoding, rest);
+
+                self.pictures.push(Picture {
+                    mime_type,
+                    picture_type,
+                    description: decode_text(encoding, description),
+                    data: data.to_vec(),
+                });
+            }
+            b"COMM" | b"COM" => {
+                let Some((language, rest)) = content.split_at_checked(3) else {
+                    return;
+                };
+
+                let (description, text) = split_terminated(encoding, rest);
+
+                self.comments.push(Comment {
+                    language: decode_text(0, language),
+                    description: decode_text(encoding, description),
+                    text: decode_text(encoding, text),
+                });
+            }
+            b"TXXX" | b"TXX" => {
+                let (description, value) = split_terminated(encoding, content);
+
+                self.user_texts.push(UserText {
+                    description: decode_text(encoding, description),
+                    value: decode_text(encoding, value),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    /// Encoder delay, end padding and the length of the encoder input in samples from the
+    /// `iTunSMPB` comment that iTunes writes for gapless playback.
+    pub fn itunes_gapless(&self) -> Option<(u32, u32, Option<u64>)> {
+        let comment = self
+            .comments
+            .iter()
+            .find(|comment| comment.description == "iTunSMPB")?;
+
+        let mut fields = comment.text.split_whitespace().skip(1);
+        let encoder_delay = u32::from_str_radix(fields.next()?, 16).ok()?;
+        let end_padding = u32::from_str_radix(fields.next()?, 16).ok()?;
+        let samples = fields
+            .next()
+            .and_then(|field| u64::from_str_radix(field, 16).ok());
+
+        Some((encoder_delay, end_padding, samples))
+    }
+}
+
+/// ID3v1 tag from the last 128 bytes of the file, with the longer texts of the Enhanced
+/// `TAG+` block in front of it when there is one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Id3v1Tag {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub year: String,
+    pub comment: String,
+    /// Track number of ID3v1.1, which takes the last two bytes of the comment.
+    pub track: Option<u8>,
+    /// Index into the genre table, `None` for 255 (no genre).
+    pub genre: Option<u8>,
+    /// Fields of the Enhanced tag only.
+    pub enhanced: Option<Id3v1Enhanced>,
+}
+
+/// Fields of the Enhanced `TAG+` block besides the title, artist and album.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Id3v1Enhanced {
+    /// 1 for slow, 2 for medium, 3 for fast and 4 for hardcore, 0 when unset.
+    pub speed: u8,
+    /// Free text genre.
+    pub genre: String,
+    /// Start and end of the music as `mmm:ss`.
+    pub start_time: String,
+    pub end_time: String,
+}
+
+/// ISO-8859-1 text padded with zeros or spaces.
+fn read_latin1(data: &[u8]) -> String {
+    decode_text(0, data)
+        .trim_end_matches(['\0', ' '])
+        .to_string()
+}
+
+impl Id3v1Tag {
+    /// Reads the tag at the end of `buffer`.
+    pub fn create_from_buffer(buffer: &Buffer) -> Option<Self> {
+        Self::create_from_bytes(&buffer.data)
+    }
+
+    /// Reads the tag at the end of `data`.
+    pub fn create_from_bytes(data: &[u8]) -> Option<Self> {
+        let start = data.len().checked_sub(ID3V1_LENGTH)?;
+        let tag = &data[start..];
+
+        if !tag.starts_with(b"TAG") {
+            return None;
+        }
+
+        let comment = &tag[97..127];
+
+        // A zero byte in front of a non-zero last byte marks the track number of ID3v1.1.
+        let (comment, track) = if comment[28] == 0 && comment[29] != 0 {
+            (&comment[..28], Some(comment[29]))
+        } else {
+            (comment, None)
+        };
+
+        let mut result = Self {
+            title: read_latin1(&tag[3..33]),
+            artist: read_latin1(&tag[33..63]),
+            album: read_latin1(&tag[63..93]),
+            year: read_latin1(&tag[93..97]),
+            comment: read_latin1(comment),
+            track,
+            genre: Some(tag[127]).filter(|&genre| genre != 255),
+            enhanced: None,
+        };
+
+        let enhanced = start
+            .checked_sub(ID3V1_ENHANCED_LENGTH)
+            .map(|enhanced_start| &data[enhanced_start..start])
+            .filter(|enhanced| enhanced.starts_with(b"TAG+"));
+
+        if let Some(enhanced) = enhanced {
+            // The Enhanced tag holds the 60 characters that follow the first 30.
+            result.title += &read_latin1(&enhanced[4..64]);
+            result.artist += &read_latin1(&enhanced[64..124]);
+            result.album += &read_latin1(&enhanced[124..184]);
+            result.enhanced = Some(Id3v1Enhanced {
+                speed: enhanced[184],
+                genre: read_latin1(&enhanced[185..215]),
+                start_time: read_latin1(&enhanced[215..221]),
+                end_time: read_latin1(&enhanced[221..227]),
+            });
+        }
+
+        Some(result)
+    }
+
+    /// Length of the tag in bytes, including the Enhanced tag.
+    pub fn length(&self) -> usize {
+        match self.enhanced {
+            Some(_) => ID3V1_ENHANCED_LENGTH + ID3V1_LENGTH,
+            None => ID3V1_LENGTH,
+        }
+    }
+
+    /// Name of the genre, `None` when unset or outside the genre table.
+    pub fn genre_name(&self) -> Option<&'static str> {
+        GENRES.get(self.genre? as usize).copied()
+    }
+}
+
+/// Length of the Lyrics3v2 block ending at the end of `data`. The block, which sits in front of
+/// an ID3v1 tag, ends with its length as six digits and `LYRICS200`.
+pub fn lyrics3v2_length(data: &[u8]) -> Option<usize> {
+    let end = data.len().checked_sub(15)?;
+    let trailer = &data[end..];
+
+    if &trailer[6..] != b"LYRICS200" {
+        return None;
+    }
+
+    let size: usize = std::str::from_utf8(&trailer[..6]).ok()?.parse().ok()?;
+    let start = end.checked_sub(size)?;
+
+    data[start..]
+        .starts_with(b"LYRICSBEGIN")
+        .then_some(size + 15)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn frame(version: u8, id: &str, flags: u8, content: &[u8]) -> Vec<u8> {
+        let mut data = id.as_bytes().to_vec();
+        let size = content.len() as u32;
+
+        match version {
+            2 => data.extend_from_slice(&size.to_be_bytes()[1..]),
+            3 => data.extend_from_slice(&size.to_be_bytes()),
+            _ => data.extend((0..4).rev().map(|i| (size >> (i * 7)) as u8 & 0x7f)),
+        }
+
+        if version > 2 {
+            data.extend_from_slice(&[0, flags]);
+        }
+
+        data.extend_from_slice(content);
+        data
+    }
+
+    fn tag(version: u8, flags: u8, body: &[u8]) -> Vec<u8> {
+        let size = body.len() as u32;
+        let mut data = vec![b'I', b'D', b'3', version, 0, flags];
+
+        data.extend((0..4).rev().map(|i| (size >> (i * 7)) as u8 & 0x7f));
+        data.extend_from_slice(body);
+        data
+    }
+
+    #[test]
+    fn test_id3v23_frames() {
+        let mut body = frame(3, "TIT2", 0, b"\0Title");
+        // UTF-16 with a little endian byte order mark.
+        body.extend(frame(3, "TPE1", 0, b"\x01\xff\xfeA\0r\0t\0\0\0"));
+        body.extend(frame(3, "TRCK", 0, b"\x037/12"));
+        body.extend(frame(3, "TYER", 0, b"\x001999"));
+        body.extend(frame(
+            3,
+            "COMM",
+            0,
+            b"\0engiTunSMPB\0 00000000 00000210 000003C4 0000000000260DAC",
+        ));
+        body.extend(frame(3, "APIC", 0, b"\0image/png\0\x03Cover\0\x89PNG"));
+        body.extend(frame(3, "TXXX", 0, b"\x03MOOD\0Calm"));
+        body.extend([0; 16]);
+
+        let data = tag(3, 0, &body);
+        let tag = Id3v2Tag::create_from_bytes(&data).unwrap();
+
+        assert_eq!(tag.version, 3);
+        assert_eq!(tag.length, data.len());
+        assert_eq!(tag.title.as_deref(), Some("Title"));
+        assert_eq!(tag.artist.as_deref(), Some("Art"));
+        assert_eq!(tag.album, None);
+        assert_eq!(tag.track.as_deref(), Some("7/12"));
+        assert_eq!(tag.year.as_deref(), Some("1999"));
+        assert_eq!(tag.comments[0].language, "eng");
+        assert_eq!(tag.itunes_gapless(), Some((0x210, 0x3c4, Some(0x260dac))));
+        assert_eq!(
+            tag.pictures,
+            vec![Picture {
+                mime_type: "image/png".to_string(),
+                picture_type: 3,
+                description: "Cover".to_string(),
+                data: b"\x89PNG".to_vec(),
+            }]
+        );
+        assert_eq!(
+            tag.user_texts,
+            vec![UserText {
+                description: "MOOD".to_string(),
+                value: "Calm".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_id3v22_frames() {
+        let mut body = frame(2, "TT2", 0, b"\0Title");
+        body.extend(frame(2, "PIC", 0, b"\0JPG\x04\0\xff\xd8"));
+
+        let tag = Id3v2Tag::create_from_bytes(&tag(2, 0, &body)).unwrap();
+
+        assert_eq!(tag.title.as_deref(), Some("Title"));
+        assert_eq!(tag.pictures[0].mime_type, "image/jpeg");
+        assert_eq!(tag.pictures[0].picture_type, 4);
+        assert_eq!(tag.pictures[0].data, [0xff, 0xd8]);
+    }
+
+    #[test]
+    fn test_id3v24_frames() {
+        // Extended header of 6 bytes, then an unsynchronised frame with a data length
+        // indicator.
+        let mut body = vec![0, 0, 0, 6, 1, 0];
+        body.extend(frame(4, "TALB", 0x03, b"\0\0\0\x03\0\xff\0\xe9"));
+        body.extend(frame(4, "TDRC", 0, b"\x032001-09-11"));
+
+        let mut data = tag(4, FLAG_EXTENDED_HEADER | FLAG_FOOTER, &body);
+        data.extend_from_slice(b"3DI\x04\0\x50");
+        data.extend_from_within(6..10);
+
+        let tag = Id3v2Tag::create_from_bytes(&data).unwrap();
+
+        assert_eq!(tag.length, data.len());
+        assert_eq!(tag.album.as_deref(), Some("\u{ff}\u{e9}"));
+        assert_eq!(tag.year.as_deref(), Some("2001-09-11"));
+    }
+
+    #[test]
+    fn test_unsynchronisation() {
+        assert_eq!(
+            remove_unsynchronisation(&[0xff, 0x00, 0xe0, 0xff, 0x00, 0x00]),
+            [0xff, 0xe0, 0xff, 0x00]
+        );
+
+        // The frame size counts the bytes after the zero following 0xff is removed.
+        let mut body = frame(3, "TIT2", 0, b"\0\xff\xe9t\xe9");
+        body.insert(12, 0);
+        let tag = Id3v2Tag::create_from_bytes(&tag(3, FLAG_UNSYNCHRONISATION, &body)).unwrap();
+
+        assert_eq!(tag.title.as_deref(), Some("\u{ff}\u{e9}t\u{e9}"));
+    }
+
+    #[test]
+    fn test_no_tag() {
+        let buffer = Buffer::create_buffer_from_file("mp3-examples/test_data_100kb.mp3").unwrap();
+
+        assert_eq!(Id3v2Tag::create_from_buffer(&buffer), None);
+        assert_eq!(Id3v2Tag::create_from_bytes(b"ID3\x03\0\0\0\0\x80\0"), None);
+    }
+
+    fn id3v1(title: &str, comment: &[u8; 30], genre: u8) -> Vec<u8> {
+        let mut data = b"TAG".to_vec();
+
+        for (text, length) in [(title, 30), ("Artist", 30), ("Album", 30), ("2003", 4)] {
+            data.extend_from_slice(text.as_bytes());
+            data.resize(data.len() + length - text.len(), 0);
+        }
+
+        data.extend_from_slice(comment);
+        data.push(genre);
+        data
+    }
+
+    #[test]
+    fn test_id3v1_tag() {
+        let mut comment = [b' '; 30];
+        comment[..4].copy_from_slice(b"Nice");
+
+        let tag = Id3v1Tag::create_from_bytes(&id3v1("Title", &comment, 17)).unwrap();
+
+        assert_eq!(tag.title, "Title");
+        assert_eq!(tag.artist, "Artist");
+        assert_eq!(tag.album, "Album");
+        assert_eq!(tag.year, "2003");
+        assert_eq!(tag.comment, "Nice");
+        assert_eq!(tag.track, None);
+        assert_eq!(tag.genre_name(), Some("Rock"));
+        assert_eq!(tag.length(), 128);
+
+        // ID3v1.1 with a track number and no genre.
+        let mut comment = [0; 30];
+        comment[29] = 5;
+
+        let tag = Id3v1Tag::create_from_bytes(&id3v1("Title", &comment, 255)).unwrap();
+
+        assert_eq!(tag.comment, "");
+        assert_eq!(tag.track, Some(5));
+        assert_eq!(tag.genre, None);
+        assert_eq!(GENRES[191], "Psybient");
+
+        assert_eq!(Id3v1Tag::create_from_bytes(&[0; 128]), None);
+    }
+
+    #[test]
+    fn test_id3v1_enhanced_tag() {
+        let mut data = b"TAG+".to_vec();
+
+        for (text, length) in [
+            (" More Room", 60),
+            ("", 60),
+            ("", 60),
+            ("\x02Trip Hop", 31),
+            ("001:30", 6),
+            ("004:15", 6),
+        ] {
+            data.extend_from_slice(text.as_bytes());
+            data.resize(data.len() + length - text.len(), 0);
+        }
+
+        data.extend(id3v1("A Really Long Title That Needs", &[0; 30], 27));
+
+        let tag = Id3v1Tag::create_from_bytes(&data).unwrap();
+
+        assert_eq!(tag.title, "A Really Long Title That Needs More Room");
+        assert_eq!(tag.artist, "Artist");
+        assert_eq!(tag.genre_name(), Some("Trip-Hop"));
+        assert_eq!(tag.length(), data.len());
+        assert_eq!(
+            tag.enhanced,
+            Some(Id3v1Enhanced {
+                speed: 2,
+                genre: "Trip Hop".to_string(),
+                start_time: "001:30".to_string(),
+                end_time: "004:15".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_lyrics3v2() {
+        let mut data = b"audioLYRICSBEGININD00002".to_vec();
+        data.extend_from_slice(b"10000021LYRICS200");
+
+        assert_eq!(lyrics3v2_length(&data), Some(data.len() - 5));
+
+        data[5] = b'l';
+        assert_eq!(lyrics3v2_length(&data), None);
+    }
+}
@@ -0,0 +1,212 @@
+use std::{f32::consts::PI, sync::OnceLock};
+
+use crate::{constant, side_info::GranuleInfo};
+
+struct ImdctTables {
+    cos_long: [[f32; 18]; 36],
+    cos_short: [[f32; 6]; 12],
+    windows: [[f32; 36]; 4],
+}
+
+fn tables() -> &'static ImdctTables {
+    static TABLES: OnceLock<ImdctTables> = OnceLock::new();
+
+    TABLES.get_or_init(|| {
+        let mut cos_long = [[0f32; 18]; 36];
+        for (i, row) in cos_long.iter_mut().enumerate() {
+            for (k, value) in row.iter_mut().enumerate() {
+                *value = (PI / 72.0 * (2 * i + 1 + 18) as f32 * (2 * k + 1) as f32).cos();
+            }
+        }
+
+        let mut cos_short = [[0f32; 6]; 12];
+        for (i, row) in cos_short.iter_mut().enumerate() {
+            for (k, value) in row.iter_mut().enumerate() {
+                *value = (PI / 24.0 * (2 * i + 1 + 6) as f32 * (2 * k + 1) as f32).cos();
+            }
+        }
+
+        let long = |i: usize| (PI / 36.0 * (i as f32 + 0.5)).sin();
+        let short = |i: usize| (PI / 12.0 * (i as f32 + 0.5)).sin();
+
+        let normal: [f32; 36] = std::array::from_fn(long);
+
+        let start: [f32; 36] = std::array::from_fn(|i| match i {
+            0..=17 => long(i),
+            18..=23 => 1.0,
+            24..=29 => short(i - 18),
+            _ => 0.0,
+        });
+
+        // A single short window, applied to each of the three short IMDCTs.
+        let short_block: [f32; 36] = std::array::from_fn(|i| if i < 12 { short(i) } else { 0.0 });
+
+        let stop: [f32; 36] = std::array::from_fn(|i| match i {
+            0..=5 => 0.0,
+            6..=11 => short(i - 6),
+            12..=17 => 1.0,
+            _ => long(i),
+        });
+
+        // Indexed by block type.
+        let windows = [normal, start, short_block, stop];
+
+        ImdctTables {
+            cos_long,
+            cos_short,
+            windows,
+        }
+    })
+}
+
+/// Per-channel IMDCT state, holding the second half of the previous granule's output of each
+/// subband so it can be overlap-added with the next one.
+pub struct Imdct {
+    overlap: [[f32; 18]; 32],
+}
+
+impl Default for Imdct {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Imdct {
+    pub fn new() -> Self {
+        Self {
+            overlap: [[0.0; 18]; 32],
+        }
+    }
+
+    fn imdct_long(input: &[f32], window: &[f32; 36]) -> [f32; 36] {
+        let tables = tables();
+        let mut output = [0f32; 36];
+
+        for (i, value) in output.iter_mut().enumerate() {
+            let sum: f32 = input
+                .iter()
+                .zip(&tables.cos_long[i])
+                .map(|(x, cos)| x * cos)
+                .sum();
+
+            *value = sum * window[i];
+        }
+
+        output
+    }
+
+    fn imdct_short(input: &[f32], window: &[f32; 36]) -> [f32; 36] {
+        let tables = tables();
+        let mut output = [0f32; 36];
+
+        for short_window in 0..3 {
+            for i in 0..12 {
+                let sum: f32 = (0..6)
+                    .map(|k| input[k * 3 + short_window] * tables.cos_short[i][k])
+                    .sum();
+
+                output[6 + short_window * 6 + i] += sum * window[i];
+            }
+        }
+
+        output
+    }
+
+    /// Turns the reordered and alias reduced spectrum of one granule into 18 time samples per
+    /// subband, stored as `subband * 18 + sample`, with odd subbands frequency inverted.
+    pub fn process(
+        &mut self,
+        granule: &GranuleInfo,
+        sample_rate_index: usize,
+        spectrum: &[f32; 576],
+    ) -> [f32; 576] {
+        let windows = &tables().windows;
+        let mut samples = [0f32; 576];
+        let long_subbands = constant::mixed_block_long_subbands(sample_rate_index);
+
+        for subband in 0..32 {
+            let input = &spectrum[subband * 18..subband * 18 + 18];
+
+            let is_short = granule.windows_switching
+                && granule.block_type == 2
+                && !(granule.mixed_block_flag && subband < long_subbands);
+
+            let output = if is_short {
+                Self::imdct_short(input, &windows[2])
+            } else if granule.windows_switching && granule.block_type != 2 {
+                Self::imdct_long(input, &windows[granule.block_type as usize])
+            } else {
+                Self::imdct_long(input, &windows[0])
+            };
+
+            let overlap = &mut self.overlap[subband];
+
+            for i in 0..18 {
+                let mut sample = output[i] + overlap[i];
+
+                if subband % 2 == 1 && i % 2 == 1 {
+                    sample = -sample;
+                }
+
+                samples[subband * 18 + i] = sample;
+                overlap[i] = output[i + 18];
+            }
+        }
+
+        samples
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_imdct_long_block_overlap() {
+        let mut imdct = Imdct::new();
+        let granule = GranuleInfo::new();
+
+        let mut spectrum = [0f32; 576];
+        spectrum[0] = 1.0;
+
+        let first = imdct.process(&granule, 0, &spectrum);
+        assert!((first[0] - 0.029_468_83).abs() < 1e-6);
+
+        let second = imdct.process(&granule, 0, &[0.0; 576]);
+        assert!((second[17] - -0.032_159_59).abs() < 1e-6);
+        assert!(second[18..].iter().all(|&sample| sample == 0.0));
+
+        let third = imdct.process(&granule, 0, &[0.0; 576]);
+        assert!(third.iter().all(|&sample| sample == 0.0));
+    }
+
+    #[test]
+    fn test_imdct_frequency_inversion() {
+        let mut imdct = Imdct::new();
+        let granule = GranuleInfo::new();
+
+        let mut spectrum = [0f32; 576];
+        spectrum[0] = 1.0;
+        spectrum[18] = 1.0;
+
+        let samples = imdct.process(&granule, 0, &spectrum);
+
+        assert_eq!(samples[18], samples[0]);
+        assert_eq!(samples[19], -samples[1]);
+    }
+
+    #[test]
+    fn test_imdct_short_block_windows() {
+        let mut imdct = Imdct::new();
+        let mut granule = GranuleInfo::new();
+        granule.windows_switching = true;
+        granule.block_type = 2;
+
+        let spectrum = [1f32; 576];
+        let samples = imdct.process(&granule, 0, &spectrum);
+
+        // The first short window starts at sample 6, so the first six samples only see overlap.
+        assert!(samples[..6].iter().all(|&sample| sample == 0.0));
+        assert!(samples[6] != 0.0);
+    }
+}
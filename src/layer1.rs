@@ -0,0 +1,167 @@
+use crate::{
+    buffer::Buffer,
+    crc,
+    error::ErrorType,
+    header::Header,
+    layer2::{dequantize, scale},
+};
+
+/// Subband samples of one channel, laid out as `subband * 12 + slot`.
+pub type SubbandSamples = [f32; 384];
+
+/// Bit allocation and scalefactors of a Layer I frame. The samples follow in the bitstream and
+/// are read with `decode_samples`.
+#[derive(Debug)]
+pub struct Layer1Frame {
+    pub header: Header,
+    pub crc: Option<u16>,
+    pub length_byte: usize,
+    /// Bits per sample of each subband and channel, 0 where no bits are allocated.
+    pub bits: [[u8; 2]; 32],
+    /// Scalefactor index of each subband and channel.
+    pub scale_factor: [[u8; 2]; 32],
+    /// First subband coded in intensity stereo, where both channels share the samples.
+    bound: usize,
+}
+
+impl Layer1Frame {
+    pub fn create_from_header(buffer: &mut Buffer, header: Header) -> Result<Self, ErrorType> {
+        let crc = if header.error_protection {
+            Some(buffer.get_bits(16)? as u16)
+        } else {
+            None
+        };
+
+        let channels = header.get_channels();
+        let bound = header.get_intensity_bound();
+        let mut bits = [[0u8; 2]; 32];
+
+        for (subband, subband_bits) in bits.iter_mut().enumerate() {
+            for channel in 0..channels {
+                if subband >= bound && channel == 1 {
+                    subband_bits[1] = subband_bits[0];
+                    continue;
+                }
+
+                // Allocation n means n + 1 bits per sample, 15 is forbidden.
+                subband_bits[channel] = match buffer.get_bits(4)? {
+                    0 => 0,
+                    15 => return Err(ErrorType::InvalidBitAllocation),
+                    allocation => allocation as u8 + 1,
+                };
+            }
+        }
+
+        let mut scale_factor = [[0u8; 2]; 32];
+
+        for (subband_bits, subband_scale_factor) in bits.iter().zip(scale_factor.iter_mut()) {
+            for (&channel_bits, channel_scale_factor) in
+                subband_bits.iter().zip(subband_scale_factor)
+            {
+                if channel_bits != 0 {
+                    *channel_scale_factor = buffer.get_bits(6)? as u8;
+                }
+            }
+        }
+
+        let length_byte = header.get_frame_length()?;
+
+        Ok(Self {
+            header,
+            crc,
+            length_byte,
+            bits,
+            scale_factor,
+            bound,
+        })
+    }
+
+    /// Reads the 12 samples per subband and scales them, giving one `SubbandSamples` per channel.
+    pub fn decode_samples(&self, buffer: &mut Buffer) -> Result<Vec<SubbandSamples>, ErrorType> {
+        let channels = self.header.get_channels();
+        let mut samples = vec![[0f32; 384]; channels];
+
+        for slot in 0..12 {
+            for subband in 0..32 {
+                let mut value = 0.0;
+
+                for (channel, channel_samples) in samples.iter_mut().enumerate() {
+                    let bits = self.bits[subband][channel] as u32;
+
+                    if bits == 0 {
+                        continue;
+                    }
+
+                    if subband < self.bound || channel == 0 {
+                        value = dequantize(buffer.get_bits(bits)?, (1 << bits) - 1);
+                    }
+
+                    channel_samples[subband * 12 + slot] =
+                        value * scale(self.scale_factor[subband][channel]);
+                }
+            }
+        }
+
+        Ok(samples)
+    }
+
+    /// The CRC of Layer I covers the bit allocation, 4 bits per subband of each channel up to the
+    /// intensity bound and 4 bits per subband above it.
+    pub fn check_crc(&self, buffer: &Buffer) -> Option<bool> {
+        let crc = self.crc?;
+        let start = self.header.pos / 8;
+        let channels = self.header.get_channels();
+        let protected_bits = 4 * (self.bound * channels + 32 - self.bound);
+
+        let mut data = buffer.data.get(start + 2..start + 4)?.to_vec();
+        data.extend_from_slice(buffer.data.get(start + 6..start + 6 + protected_bits / 8)?);
+
+        Some(crc::crc16(&data, 16 + protected_bits) == crc)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// MPEG-1 Layer I, 32 kbit/s, 48 kHz, mono: 8 slots of 4 bytes.
+    const HEADER: [u8; 4] = [0xff, 0xff, 0x14, 0xc0];
+
+    #[test]
+    fn test_decode_samples() {
+        // Two bits per sample in subband 0 and nothing in the others.
+        let mut fields = vec![(1, 4)];
+        fields.extend([(0, 4); 31]);
+        fields.push((3, 6));
+        fields.extend((0..12).map(|slot| (slot % 3, 2)));
+
+        let mut buffer = Buffer::create_buffer_from_fields(&HEADER, &fields, 32);
+        let header = Header::create_from_buffer(&mut buffer).unwrap();
+        let frame = Layer1Frame::create_from_header(&mut buffer, header).unwrap();
+
+        assert_eq!(frame.length_byte, 32);
+        assert_eq!(frame.bits[0], [2, 0]);
+        assert_eq!(frame.scale_factor[0], [3, 0]);
+
+        let samples = frame.decode_samples(&mut buffer).unwrap();
+
+        for slot in (0..12).step_by(3) {
+            assert!((samples[0][slot] + 2.0 / 3.0).abs() < 1e-6);
+            assert_eq!(samples[0][slot + 1], 0.0);
+            assert!((samples[0][slot + 2] - 2.0 / 3.0).abs() < 1e-6);
+        }
+
+        assert!(samples[0][12..].iter().all(|&sample| sample == 0.0));
+    }
+
+    #[test]
+    fn test_forbidden_bit_allocation() {
+        let mut buffer = Buffer::create_buffer_from_fields(&HEADER, &[(15, 4)], 32);
+        let header = Header::create_from_buffer(&mut buffer).unwrap();
+
+        assert!(matches!(
+            Layer1Frame::create_from_header(&mut buffer, header),
+            Err(ErrorType::InvalidBitAllocation)
+        ));
+    }
+}
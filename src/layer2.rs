@@ -0,0 +1,411 @@
+use crate::{
+    buffer::Buffer,
+    crc,
+    error::ErrorType,
+    header::{Header, Version},
+};
+
+/// Subband samples of one channel, laid out as `subband * 36 + slot`.
+pub type SubbandSamples = [f32; 1152];
+
+/// One row of the allocation tables: the width of the allocation field and the number of
+/// quantization steps for each allocation, 0 meaning the subband is not transmitted.
+struct Allocation {
+    bits: u32,
+    steps: &'static [u16],
+}
+
+/// Allocation table of ISO/IEC 11172-3 Table B.2 or ISO/IEC 13818-3 Table B.1, as the
+/// allocation of each subband below `sblimit`.
+struct AllocationTable {
+    sblimit: usize,
+    rows: &'static [(usize, Allocation)],
+}
+
+const STEPS_HIGH: &[u16] = &[
+    0, 3, 7, 15, 31, 63, 127, 255, 511, 1023, 2047, 4095, 8191, 16383, 32767, 65535,
+];
+const STEPS_MIDDLE: &[u16] = &[
+    0, 3, 5, 7, 9, 15, 31, 63, 127, 255, 511, 1023, 2047, 4095, 8191, 65535,
+];
+const STEPS_LOW: &[u16] = &[0, 3, 5, 7, 9, 15, 31, 65535];
+const STEPS_TOP: &[u16] = &[0, 3, 5, 65535];
+
+const STEPS_LOW_RATE_HIGH: &[u16] = &[
+    0, 3, 5, 9, 15, 31, 63, 127, 255, 511, 1023, 2047, 4095, 8191, 16383, 32767,
+];
+const STEPS_LOW_RATE_LOW: &[u16] = &[0, 3, 5, 9, 15, 31, 63, 127];
+
+const STEPS_LSF_HIGH: &[u16] = &[
+    0, 3, 5, 7, 9, 15, 31, 63, 127, 255, 511, 1023, 2047, 4095, 8191, 16383,
+];
+const STEPS_LSF_MIDDLE: &[u16] = &[0, 3, 5, 9, 15, 31, 63, 127];
+const STEPS_LSF_LOW: &[u16] = &[0, 3, 5, 9];
+
+/// Tables B.2a and B.2b, used from 56 kbit/s per channel. They only differ in `sblimit`.
+const ALLOCATION_HIGH_RATE: [(usize, Allocation); 4] = [
+    (
+        3,
+        Allocation {
+            bits: 4,
+            steps: STEPS_HIGH,
+        },
+    ),
+    (
+        11,
+        Allocation {
+            bits: 4,
+            steps: STEPS_MIDDLE,
+        },
+    ),
+    (
+        23,
+        Allocation {
+            bits: 3,
+            steps: STEPS_LOW,
+        },
+    ),
+    (
+        32,
+        Allocation {
+            bits: 2,
+            steps: STEPS_TOP,
+        },
+    ),
+];
+
+/// Tables B.2c and B.2d, used up to 48 kbit/s per channel.
+const ALLOCATION_LOW_RATE: [(usize, Allocation); 2] = [
+    (
+        2,
+        Allocation {
+            bits: 4,
+            steps: STEPS_LOW_RATE_HIGH,
+        },
+    ),
+    (
+        32,
+        Allocation {
+            bits: 3,
+            steps: STEPS_LOW_RATE_LOW,
+        },
+    ),
+];
+
+const ALLOCATION_LSF: [(usize, Allocation); 3] = [
+    (
+        4,
+        Allocation {
+            bits: 4,
+            steps: STEPS_LSF_HIGH,
+        },
+    ),
+    (
+        11,
+        Allocation {
+            bits: 3,
+            steps: STEPS_LSF_MIDDLE,
+        },
+    ),
+    (
+        32,
+        Allocation {
+            bits: 2,
+            steps: STEPS_LSF_LOW,
+        },
+    ),
+];
+
+const TABLE_A: AllocationTable = AllocationTable {
+    sblimit: 27,
+    rows: &ALLOCATION_HIGH_RATE,
+};
+const TABLE_B: AllocationTable = AllocationTable {
+    sblimit: 30,
+    rows: &ALLOCATION_HIGH_RATE,
+};
+const TABLE_C: AllocationTable = AllocationTable {
+    sblimit: 8,
+    rows: &ALLOCATION_LOW_RATE,
+};
+const TABLE_D: AllocationTable = AllocationTable {
+    sblimit: 12,
+    rows: &ALLOCATION_LOW_RATE,
+};
+const TABLE_LSF: AllocationTable = AllocationTable {
+    sblimit: 30,
+    rows: &ALLOCATION_LSF,
+};
+
+impl AllocationTable {
+    /// MPEG-1 picks the table from the bitrate per channel and the sampling frequency, MPEG-2
+    /// always uses the same one.
+    fn select(header: &Header) -> Result<&'static Self, ErrorType> {
+        if header.version != Version::MPEG1 {
+            return Ok(&TABLE_LSF);
+        }
+
+        let bitrate = header.get_bitrate()? as usize / header.get_channels();
+
+        Ok(match (bitrate, header.frequency) {
+            (..=48, 2) => &TABLE_D,
+            (..=48, _) => &TABLE_C,
+            (..=80, _) | (_, 1) => &TABLE_A,
+            _ => &TABLE_B,
+        })
+    }
+
+    fn allocation(&self, subband: usize) -> &'static Allocation {
+        let (_, allocation) = self
+            .rows
+            .iter()
+            .find(|(end, _)| subband < *end)
+            .unwrap_or(&self.rows[self.rows.len() - 1]);
+
+        allocation
+    }
+}
+
+/// Reads the three consecutive samples of a subband coded with `steps` quantization steps and
+/// dequantizes them to the -1.0..1.0 range. 3, 5 and 9 steps pack the three values into a
+/// single code word.
+fn read_samples(buffer: &mut Buffer, steps: u16) -> Result<[f32; 3], ErrorType> {
+    let steps = steps as u32;
+    let mut codes = [0u32; 3];
+
+    match steps {
+        3 | 5 | 9 => {
+            let mut code = buffer.get_bits(match steps {
+                3 => 5,
+                5 => 7,
+                _ => 10,
+            })?;
+
+            for value in codes.iter_mut() {
+                *value = code % steps;
+                code /= steps;
+            }
+        }
+        _ => {
+            let bits = (steps + 1).trailing_zeros();
+
+            for value in codes.iter_mut() {
+                *value = buffer.get_bits(bits)?;
+            }
+        }
+    }
+
+    Ok(codes.map(|code| dequantize(code, steps)))
+}
+
+/// Maps a sample code of `steps` quantization steps to the -1.0..1.0 range, shared with Layer I.
+pub fn dequantize(code: u32, steps: u32) -> f32 {
+    2.0 * (code as f32 - (steps / 2) as f32) / steps as f32
+}
+
+/// Scalefactor of ISO/IEC 11172-3 Table B.1, from 2.0 down in steps of `2^(-1/3)`.
+pub fn scale(index: u8) -> f32 {
+    (1.0 - index as f32 / 3.0).exp2()
+}
+
+/// Bit allocation and scalefactors of a Layer II frame. The samples follow in the bitstream and
+/// are read with `decode_samples`.
+#[derive(Debug)]
+pub struct Layer2Frame {
+    pub header: Header,
+    pub crc: Option<u16>,
+    pub length_byte: usize,
+    /// Quantization steps of each subband and channel, 0 where no bits are allocated.
+    pub steps: [[u16; 2]; 32],
+    /// Scalefactor indices of the three parts of 12 slots of each subband and channel.
+    pub scale_factor: [[[u8; 3]; 2]; 32],
+    sblimit: usize,
+    /// First subband coded in intensity stereo, where both channels share the samples.
+    bound: usize,
+    /// Length of the bit allocation and scfsi, the part of the frame covered by the CRC.
+    protected_bits: usize,
+}
+
+impl Layer2Frame {
+    pub fn create_from_header(buffer: &mut Buffer, header: Header) -> Result<Self, ErrorType> {
+        let crc = if header.error_protection {
+            Some(buffer.get_bits(16)? as u16)
+        } else {
+            None
+        };
+
+        let table = AllocationTable::select(&header)?;
+        let channels = header.get_channels();
+        let sblimit = table.sblimit;
+
+        let bound = header.get_intensity_bound().min(sblimit);
+
+        let start = buffer.pos;
+        let mut steps = [[0u16; 2]; 32];
+
+        for (subband, subband_steps) in steps.iter_mut().enumerate().take(sblimit) {
+            let allocation = table.allocation(subband);
+
+            for channel in 0..channels {
+                subband_steps[channel] = if subband < bound || channel == 0 {
+                    allocation.steps[buffer.get_bits(allocation.bits)? as usize]
+                } else {
+                    subband_steps[0]
+                };
+            }
+        }
+
+        let mut scfsi = [[0u8; 2]; 32];
+
+        for (subband_steps, subband_scfsi) in steps.iter().zip(scfsi.iter_mut()).take(sblimit) {
+            for (&channel_steps, channel_scfsi) in subband_steps.iter().zip(subband_scfsi) {
+                if channel_steps != 0 {
+                    *channel_scfsi = buffer.get_bits(2)? as u8;
+                }
+            }
+        }
+
+        let protected_bits = buffer.pos - start;
+        let mut scale_factor = [[[0u8; 3]; 2]; 32];
+
+        for subband in 0..sblimit {
+            for channel in 0..channels {
+                if steps[subband][channel] == 0 {
+                    continue;
+                }
+
+                let first = buffer.get_bits(6)? as u8;
+
+                // scfsi tells which of the three parts share a transmitted scalefactor.
+                scale_factor[subband][channel] = match scfsi[subband][channel] {
+                    0 => [first, buffer.get_bits(6)? as u8, buffer.get_bits(6)? as u8],
+                    1 => [first, first, buffer.get_bits(6)? as u8],
+                    2 => [first; 3],
+                    _ => {
+                        let second = buffer.get_bits(6)? as u8;
+                        [first, second, second]
+                    }
+                };
+            }
+        }
+
+        let length_byte = header.get_frame_length()?;
+
+        Ok(Self {
+            header,
+            crc,
+            length_byte,
+            steps,
+            scale_factor,
+            sblimit,
+            bound,
+            protected_bits,
+        })
+    }
+
+    /// Reads the 12 groups of three samples per subband and scales them, giving one
+    /// `SubbandSamples` per channel.
+    pub fn decode_samples(&self, buffer: &mut Buffer) -> Result<Vec<SubbandSamples>, ErrorType> {
+        let channels = self.header.get_channels();
+        let mut samples = vec![[0f32; 1152]; channels];
+
+        for group in 0..12 {
+            for subband in 0..self.sblimit {
+                let mut values = [0f32; 3];
+
+                for (channel, channel_samples) in samples.iter_mut().enumerate() {
+                    let steps = self.steps[subband][channel];
+
+                    if steps == 0 {
+                        continue;
+                    }
+
+                    if subband < self.bound || channel == 0 {
+                        values = read_samples(buffer, steps)?;
+                    }
+
+                    let factor = scale(self.scale_factor[subband][channel][group / 4]);
+
+                    for (i, value) in values.iter().enumerate() {
+                        channel_samples[subband * 36 + group * 3 + i] = value * factor;
+                    }
+                }
+            }
+        }
+
+        Ok(samples)
+    }
+
+    pub fn check_crc(&self, buffer: &Buffer) -> Option<bool> {
+        let crc = self.crc?;
+        let start = self.header.pos / 8;
+        let protected_bytes = self.protected_bits.div_ceil(8);
+
+        let mut data = buffer.data.get(start + 2..start + 4)?.to_vec();
+        data.extend_from_slice(buffer.data.get(start + 6..start + 6 + protected_bytes)?);
+
+        Some(crc::crc16(&data, 16 + self.protected_bits) == crc)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::header::Mode;
+
+    use super::*;
+
+    /// MPEG-1 Layer II, 32 kbit/s, 48 kHz, mono: allocation table C and 96 byte frames.
+    const HEADER: [u8; 4] = [0xff, 0xfd, 0x14, 0xc0];
+
+    #[test]
+    fn test_allocation_table_select() {
+        let mut buffer = Buffer::create_buffer_from_fields(&HEADER, &[], 96);
+        let mut header = Header::create_from_buffer(&mut buffer).unwrap();
+        assert_eq!(AllocationTable::select(&header).unwrap().sblimit, 8);
+
+        header.frequency = 2;
+        assert_eq!(AllocationTable::select(&header).unwrap().sblimit, 12);
+
+        header.bitrate = 0b1010;
+        assert_eq!(AllocationTable::select(&header).unwrap().sblimit, 30);
+
+        header.bitrate = 0b1000;
+        header.mode = Mode::Stereo;
+        assert_eq!(AllocationTable::select(&header).unwrap().sblimit, 27);
+
+        header.version = Version::MPEG2;
+        assert_eq!(AllocationTable::select(&header).unwrap().sblimit, 30);
+    }
+
+    #[test]
+    fn test_decode_samples() {
+        let mut fields = vec![(1, 4), (0, 4)];
+        // Subbands 2..8 use 3 bit allocations and are left out.
+        fields.extend([(0, 3); 6]);
+        // One scalefactor for all three parts, index 3 scales by 1.0.
+        fields.extend([(2, 2), (3, 6)]);
+        // Grouped code of the values 2, 1 and 0 out of 3 steps.
+        fields.extend([(2 + 3, 5); 12]);
+
+        let mut buffer = Buffer::create_buffer_from_fields(&HEADER, &fields, 96);
+        let header = Header::create_from_buffer(&mut buffer).unwrap();
+        let frame = Layer2Frame::create_from_header(&mut buffer, header).unwrap();
+
+        assert_eq!(frame.length_byte, 96);
+        assert_eq!(frame.steps[0], [3, 0]);
+        assert_eq!(frame.scale_factor[0][0], [3; 3]);
+        assert_eq!(frame.check_crc(&buffer), None);
+
+        let samples = frame.decode_samples(&mut buffer).unwrap();
+
+        assert_eq!(samples.len(), 1);
+
+        for slot in (0..36).step_by(3) {
+            assert!((samples[0][slot] - 2.0 / 3.0).abs() < 1e-6);
+            assert_eq!(samples[0][slot + 1], 0.0);
+            assert!((samples[0][slot + 2] + 2.0 / 3.0).abs() < 1e-6);
+        }
+
+        assert!(samples[0][36..].iter().all(|&sample| sample == 0.0));
+    }
+}
@@ -0,0 +1,43 @@
+//! A small mp3 decoder.
+//!
+//! ```no_run
+//! use mp3_decoder::{Buffer, Decoder};
+//!
+//! let buffer = Buffer::create_buffer_from_file("song.mp3")?;
+//!
+//! for frame in Decoder::new(buffer) {
+//!     println!("{} samples at {}Hz", frame.samples.len(), frame.sample_rate);
+//! }
+//! # Ok::<(), mp3_decoder::error::ErrorType>(())
+//! ```
+
+pub mod ape;
+pub mod buffer;
+mod constant;
+mod crc;
+pub mod decoder;
+pub mod error;
+pub mod frame;
+pub mod header;
+mod huffman;
+pub mod id3;
+mod imdct;
+mod layer1;
+mod layer2;
+mod reorder;
+mod requantize;
+mod reservoir;
+pub mod side_info;
+mod stereo;
+mod synthesis;
+pub mod vbr;
+
+pub use ape::ApeTag;
+pub use buffer::Buffer;
+pub use decoder::{CrcPolicy, DecodedFrame, Decoder};
+pub use error::ErrorType;
+pub use frame::Frame;
+pub use header::{Header, Layer, Mode, Version};
+pub use id3::{Id3v1Tag, Id3v2Tag};
+pub use side_info::SideInfo;
+pub use vbr::{LameTag, VbrInfo, VbrTag};
@@ -1,25 +1,97 @@
-#![allow(unused_variables, dead_code)]
+use std::{
+    fs,
+    io::{self, BufWriter, Seek, SeekFrom, Write},
+};
 
-mod buffer;
-mod constant;
-mod error;
-mod frame;
-mod header;
-mod side_info;
+use mp3_decoder::Decoder;
 
-use buffer::Buffer;
-use header::Header;
+/// Writes a WAV header whose RIFF and data sizes are patched by `finish_wav` once all samples
+/// are written.
+fn write_wav_header(file: &mut impl Write, sample_rate: u32, channels: u16) -> io::Result<()> {
+    file.write_all(b"RIFF")?;
+    file.write_all(&36u32.to_le_bytes())?;
+    file.write_all(b"WAVEfmt ")?;
+    file.write_all(&16u32.to_le_bytes())?;
+    file.write_all(&1u16.to_le_bytes())?;
+    file.write_all(&channels.to_le_bytes())?;
+    file.write_all(&sample_rate.to_le_bytes())?;
+    file.write_all(&(sample_rate * channels as u32 * 2).to_le_bytes())?;
+    file.write_all(&(channels * 2).to_le_bytes())?;
+    file.write_all(&16u16.to_le_bytes())?;
+    file.write_all(b"data")?;
+    file.write_all(&0u32.to_le_bytes())
+}
+
+fn finish_wav(file: &mut (impl Write + Seek), data_length: u32) -> io::Result<()> {
+    file.seek(SeekFrom::Start(4))?;
+    file.write_all(&(36 + data_length).to_le_bytes())?;
+    file.seek(SeekFrom::Start(40))?;
+    file.write_all(&data_length.to_le_bytes())?;
+    file.flush()
+}
 
-use crate::frame::Frame;
+fn exit_with_error(message: &str) -> ! {
+    eprintln!("{message}");
+    std::process::exit(1);
+}
 
 fn main() {
-    let mut buffer = Buffer::create_buffer_from_file("mp3-examples/test_data_1mb.mp3");
-    buffer.set_pos(14192);
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.len() != 3 {
+        exit_with_error(&format!("Usage: {} <input.mp3 | -> <output.wav>", args[0]));
+    }
+
+    let decoder = if args[1] == "-" {
+        Decoder::from_reader(io::stdin())
+    } else {
+        match fs::File::open(&args[1]) {
+            Ok(file) => Decoder::from_reader(file),
+            Err(error) => exit_with_error(&format!("Cannot open {}: {error}", args[1])),
+        }
+    };
+
+    let mut decoder = decoder
+        .unwrap_or_else(|error| exit_with_error(&format!("Cannot read {}: {error}", args[1])));
+
+    let write_error =
+        |error: io::Error| exit_with_error(&format!("Cannot write {}: {error}", args[2]));
+
+    let mut output = None;
+    let mut data_length: u32 = 0;
+
+    loop {
+        let frame = match decoder.next_frame() {
+            Ok(Some(frame)) => frame,
+            Ok(None) => break,
+            Err(error) => exit_with_error(&format!("Cannot decode {}: {error}", args[1])),
+        };
+
+        let file = match &mut output {
+            Some(file) => file,
+            None => {
+                let file = fs::File::create(&args[2]).unwrap_or_else(|error| {
+                    exit_with_error(&format!("Cannot create {}: {error}", args[2]))
+                });
+                let mut file = BufWriter::new(file);
+
+                write_wav_header(&mut file, frame.sample_rate, frame.channels as u16)
+                    .unwrap_or_else(write_error);
+                output.insert(file)
+            }
+        };
+
+        for sample in frame.samples_i16() {
+            file.write_all(&sample.to_le_bytes())
+                .unwrap_or_else(write_error);
+        }
+
+        data_length += (frame.samples.len() * 2) as u32;
+    }
 
-    let mut frame = Frame::create_from_buffer(&mut buffer);
-    frame.decode_main_data(&mut buffer);
+    let Some(mut file) = output else {
+        exit_with_error(&format!("No MPEG audio frames found in {}", args[1]));
+    };
 
-    println!("header: {}\n\n", &frame.header);
-    println!("{:?}\n\n", &frame.side_info);
-    println!("{:?}", frame.granules_data);
+    finish_wav(&mut file, data_length).unwrap_or_else(write_error);
 }
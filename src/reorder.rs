@@ -0,0 +1,133 @@
+use crate::{constant, side_info::GranuleInfo};
+
+const ALIAS_COEFFICIENTS: [f32; 8] = [
+    -0.6, -0.535, -0.33, -0.185, -0.095, -0.041, -0.0142, -0.0037,
+];
+
+/// Reorders the short bands of a granule from band/window order into the window interleaved
+/// order expected by the IMDCT. Long bands, including those of a mixed block, are left as is.
+pub fn reorder(granule: &GranuleInfo, sample_rate_index: usize, spectrum: &mut [f32; 576]) {
+    if !(granule.windows_switching && granule.block_type == 2) {
+        return;
+    }
+
+    let band_short = &constant::SCALEFACTOR_BAND_SHORT[sample_rate_index];
+    let first_band = if granule.mixed_block_flag { 3 } else { 0 };
+    let mut reordered = [0f32; 576];
+
+    for band_index in first_band..13 {
+        let start = band_short[band_index] as usize * 3;
+        let width = (band_short[band_index + 1] - band_short[band_index]) as usize;
+
+        for window in 0..3 {
+            for line in 0..width {
+                reordered[start + line * 3 + window] = spectrum[start + window * width + line];
+            }
+        }
+    }
+
+    let start = band_short[first_band] as usize * 3;
+    spectrum[start..].copy_from_slice(&reordered[start..]);
+}
+
+/// Applies the alias reduction butterflies on every boundary between two long block subbands.
+pub fn reduce_aliases(granule: &GranuleInfo, sample_rate_index: usize, spectrum: &mut [f32; 576]) {
+    let subbands = if granule.windows_switching && granule.block_type == 2 {
+        if granule.mixed_block_flag {
+            constant::mixed_block_long_subbands(sample_rate_index)
+        } else {
+            return;
+        }
+    } else {
+        32
+    };
+
+    let mut cs = [0f32; 8];
+    let mut ca = [0f32; 8];
+
+    for (i, coefficient) in ALIAS_COEFFICIENTS.iter().enumerate() {
+        let norm = (1.0 + coefficient * coefficient).sqrt();
+
+        cs[i] = 1.0 / norm;
+        ca[i] = coefficient / norm;
+    }
+
+    for subband in 1..subbands {
+        for i in 0..8 {
+            let low = subband * 18 - 1 - i;
+            let high = subband * 18 + i;
+
+            let a = spectrum[low];
+            let b = spectrum[high];
+
+            spectrum[low] = a * cs[i] - b * ca[i];
+            spectrum[high] = b * cs[i] + a * ca[i];
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn short_granule(mixed_block_flag: bool) -> GranuleInfo {
+        let mut granule = GranuleInfo::new();
+        granule.windows_switching = true;
+        granule.block_type = 2;
+        granule.mixed_block_flag = mixed_block_flag;
+
+        granule
+    }
+
+    #[test]
+    fn test_reorder_short_block() {
+        let mut spectrum = [0f32; 576];
+        for (line, value) in spectrum.iter_mut().enumerate() {
+            *value = line as f32;
+        }
+
+        reorder(&short_granule(false), 0, &mut spectrum);
+
+        // Band 0 is 4 lines wide per window at 44.1 kHz.
+        assert_eq!(
+            spectrum[..12],
+            [0., 4., 8., 1., 5., 9., 2., 6., 10., 3., 7., 11.]
+        );
+        // Band 5 starts at line 66 and is 8 lines wide per window.
+        assert_eq!(spectrum[66..72], [66., 74., 82., 67., 75., 83.]);
+        assert_eq!(spectrum[573..], [463., 519., 575.]);
+    }
+
+    #[test]
+    fn test_reorder_mixed_block_keeps_long_part() {
+        let mut spectrum = [0f32; 576];
+        for (line, value) in spectrum.iter_mut().enumerate() {
+            *value = line as f32;
+        }
+
+        reorder(&short_granule(true), 0, &mut spectrum);
+
+        assert_eq!(spectrum[35], 35.0);
+        assert_eq!(spectrum[36..42], [36., 40., 44., 37., 41., 45.]);
+    }
+
+    #[test]
+    fn test_reduce_aliases() {
+        let mut spectrum = [0f32; 576];
+        spectrum[17] = 1.0;
+        spectrum[18] = 1.0;
+        spectrum[558] = 1.0;
+
+        reduce_aliases(&GranuleInfo::new(), 0, &mut spectrum);
+
+        assert!((spectrum[17] - 1.371_989).abs() < 1e-6);
+        assert!((spectrum[18] - 0.342_997).abs() < 1e-6);
+        assert!((spectrum[557] - 0.514_496).abs() < 1e-6);
+        assert!((spectrum[558] - 0.857_493).abs() < 1e-6);
+
+        let mut spectrum = [1f32; 576];
+        reduce_aliases(&short_granule(false), 0, &mut spectrum);
+
+        assert!(spectrum.iter().all(|&line| line == 1.0));
+    }
+}
@@ -0,0 +1,180 @@
+use crate::{constant, side_info::GranuleInfo};
+
+fn dequantize(value: i32, exponent: f32) -> f32 {
+    if value == 0 {
+        return 0.0;
+    }
+
+    let magnitude = (value.abs() as f32).powf(4.0 / 3.0) * exponent.exp2();
+
+    if value < 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+fn requantize_long(
+    granule: &GranuleInfo,
+    scale_factor: &[u8],
+    huffman_code: &[i32; 576],
+    spectrum: &mut [f32; 576],
+    band_long: &[u16; 23],
+    bands: std::ops::Range<usize>,
+) {
+    let multiplier = if granule.scalefac_scale { 1.0 } else { 0.5 };
+    let gain = 0.25 * (granule.global_gain as f32 - 210.0);
+
+    for band_index in bands {
+        let pretab = if granule.preflag {
+            constant::PRETAB[band_index]
+        } else {
+            0
+        };
+        let scale = scale_factor.get(band_index).copied().unwrap_or(0) + pretab;
+        let exponent = gain - multiplier * scale as f32;
+
+        for line in band_long[band_index] as usize..band_long[band_index + 1] as usize {
+            spectrum[line] = dequantize(huffman_code[line], exponent);
+        }
+    }
+}
+
+fn requantize_short(
+    granule: &GranuleInfo,
+    scale_factor: &[u8],
+    huffman_code: &[i32; 576],
+    spectrum: &mut [f32; 576],
+    band_short: &[u16; 14],
+    first_band: usize,
+    long_bands: usize,
+) {
+    let multiplier = if granule.scalefac_scale { 1.0 } else { 0.5 };
+    // A mixed block stores its long scalefactors before the short ones starting at band 3.
+    let offset = if first_band > 0 { long_bands } else { 0 };
+
+    for band_index in first_band..13 {
+        let start = band_short[band_index] as usize * 3;
+        let width = (band_short[band_index + 1] - band_short[band_index]) as usize;
+
+        for window in 0..3 {
+            let index = offset + (band_index - first_band) * 3 + window;
+            let scale = scale_factor.get(index).copied().unwrap_or(0);
+            let exponent = 0.25
+                * (granule.global_gain as f32 - 210.0 - 8.0 * granule.subblock_gain[window] as f32)
+                - multiplier * scale as f32;
+
+            let window_start = start + window * width;
+
+            for line in window_start..window_start + width {
+                spectrum[line] = dequantize(huffman_code[line], exponent);
+            }
+        }
+    }
+}
+
+/// Turns the Huffman decoded lines of one granule/channel into dequantized spectral values,
+/// `sign(x) * |x|^(4/3) * 2^(gain - scalefactors)`, using the long, short or mixed band layout.
+pub fn requantize(
+    granule: &GranuleInfo,
+    scale_factor: &[u8],
+    huffman_code: &[i32; 576],
+    sample_rate_index: usize,
+) -> [f32; 576] {
+    let mut spectrum = [0f32; 576];
+
+    let band_long = &constant::SCALEFACTOR_BAND_LONG[sample_rate_index];
+    let band_short = &constant::SCALEFACTOR_BAND_SHORT[sample_rate_index];
+    let long_bands = constant::mixed_block_long_bands(sample_rate_index);
+
+    if granule.windows_switching && granule.block_type == 2 {
+        if granule.mixed_block_flag {
+            requantize_long(
+                granule,
+                scale_factor,
+                huffman_code,
+                &mut spectrum,
+                band_long,
+                0..long_bands,
+            );
+            requantize_short(
+                granule,
+                scale_factor,
+                huffman_code,
+                &mut spectrum,
+                band_short,
+                3,
+                long_bands,
+            );
+        } else {
+            requantize_short(
+                granule,
+                scale_factor,
+                huffman_code,
+                &mut spectrum,
+                band_short,
+                0,
+                long_bands,
+            );
+        }
+    } else {
+        requantize_long(
+            granule,
+            scale_factor,
+            huffman_code,
+            &mut spectrum,
+            band_long,
+            0..22,
+        );
+    }
+
+    spectrum
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_requantize_long_block() {
+        let mut granule = GranuleInfo::new();
+        granule.global_gain = 214;
+
+        let mut huffman_code = [0; 576];
+        huffman_code[0] = 8;
+        huffman_code[1] = -1;
+        huffman_code[575] = 1;
+
+        let mut scale_factor = vec![0; 22];
+        scale_factor[0] = 2;
+
+        let spectrum = requantize(&granule, &scale_factor, &huffman_code, 0);
+
+        assert!((spectrum[0] - 16.0).abs() < 1e-4);
+        assert_eq!(spectrum[1], -1.0);
+        assert_eq!(spectrum[2], 0.0);
+        assert_eq!(spectrum[575], 2.0);
+    }
+
+    #[test]
+    fn test_requantize_short_block_with_preflag_ignored() {
+        let mut granule = GranuleInfo::new();
+        granule.global_gain = 210;
+        granule.windows_switching = true;
+        granule.block_type = 2;
+        granule.preflag = true;
+        granule.subblock_gain = [0, 1, 0];
+
+        let mut huffman_code = [0; 576];
+        // Band 0 has a width of 4 lines per window at 44.1 kHz.
+        huffman_code[0] = 1;
+        huffman_code[4] = 1;
+        huffman_code[8] = 1;
+
+        let spectrum = requantize(&granule, &[0; 39], &huffman_code, 0);
+
+        assert_eq!(spectrum[0], 1.0);
+        assert_eq!(spectrum[4], 0.25);
+        assert_eq!(spectrum[8], 1.0);
+    }
+}
@@ -0,0 +1,97 @@
+use crate::buffer::Buffer;
+
+/// `main_data_begin` is a 9 bit field, so a frame never looks back further than this.
+const MAX_MAIN_DATA_BEGIN: usize = 511;
+
+/// The bit reservoir: main data bytes of previous frames, without their headers, CRCs and side
+/// info, which the next frames can start their own main data in.
+pub struct Reservoir {
+    data: Vec<u8>,
+}
+
+impl Default for Reservoir {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Reservoir {
+    pub fn new() -> Self {
+        Self {
+            data: Vec::with_capacity(MAX_MAIN_DATA_BEGIN),
+        }
+    }
+
+    /// Forgets all stored bytes, e.g. when the stream was not contiguous.
+    pub fn clear(&mut self) {
+        self.data.clear();
+    }
+
+    /// Returns a buffer holding the last `main_data_begin` bytes of the reservoir followed by the
+    /// main data bytes of the current frame, then appends those bytes to the reservoir.
+    /// Returns `None` when the reservoir does not hold enough bytes yet, which happens for the
+    /// first frames after the start of a stream or a resync.
+    pub fn main_data(
+        &mut self,
+        main_data_begin: usize,
+        frame_data: &[u8],
+    ) -> Option<Buffer<'static>> {
+        let main_data = if main_data_begin <= self.data.len() {
+            let mut main_data = self.data[self.data.len() - main_data_begin..].to_vec();
+            main_data.extend_from_slice(frame_data);
+
+            Some(Buffer::create_buffer_from_bytes(main_data))
+        } else {
+            None
+        };
+
+        self.data.extend_from_slice(frame_data);
+
+        if self.data.len() > MAX_MAIN_DATA_BEGIN {
+            self.data.drain(..self.data.len() - MAX_MAIN_DATA_BEGIN);
+        }
+
+        main_data
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_main_data_look_back() {
+        let mut reservoir = Reservoir::new();
+
+        let buffer = reservoir.main_data(0, &[1, 2, 3]).unwrap();
+        assert_eq!(*buffer.data, [1, 2, 3]);
+
+        let buffer = reservoir.main_data(2, &[4, 5]).unwrap();
+        assert_eq!(*buffer.data, [2, 3, 4, 5]);
+        assert_eq!(buffer.total_bits, 32);
+
+        assert!(reservoir.main_data(6, &[6]).is_none());
+        assert_eq!(
+            *reservoir.main_data(6, &[]).unwrap().data,
+            [1, 2, 3, 4, 5, 6]
+        );
+    }
+
+    #[test]
+    fn test_main_data_is_bounded() {
+        let mut reservoir = Reservoir::new();
+
+        reservoir.main_data(0, &[0; 400]);
+        reservoir.main_data(0, &[1; 400]);
+
+        assert!(reservoir.main_data(512, &[]).is_none());
+
+        let buffer = reservoir.main_data(511, &[]).unwrap();
+        assert_eq!(buffer.data.len(), 511);
+        assert_eq!(buffer.data[110], 0);
+        assert_eq!(buffer.data[111], 1);
+
+        reservoir.clear();
+        assert!(reservoir.main_data(1, &[]).is_none());
+    }
+}
@@ -1,4 +1,8 @@
-use crate::{buffer::Buffer, error::ErrorType, header::Mode};
+use crate::{
+    buffer::Buffer,
+    error::ErrorType,
+    header::{Header, Version},
+};
 
 #[derive(Debug)]
 pub struct SideInfo {
@@ -13,7 +17,7 @@ pub struct GranuleInfo {
     pub part_23_length: u16,
     pub big_values: u16,
     pub global_gain: u8,
-    pub scalefac_compress: u8,
+    pub scalefac_compress: u16,
     pub windows_switching: bool,
     pub block_type: u8,
     pub mixed_block_flag: bool,
@@ -25,8 +29,14 @@ pub struct GranuleInfo {
     pub count1_table_select: bool,
 }
 
+impl Default for GranuleInfo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl GranuleInfo {
-    fn new() -> Self {
+    pub fn new() -> Self {
         Self {
             part_23_length: 0,
             big_values: 0,
@@ -46,62 +56,78 @@ impl GranuleInfo {
 }
 
 impl SideInfo {
-    pub fn create_from_buffer(buffer: &mut Buffer, mode: &Mode) -> Result<Self, ErrorType> {
-        let is_mono = *mode == Mode::SingleChannel;
-
-        let main_data_begin = buffer.get_bits(9).unwrap() as u16;
-        let private_bits: u8 = buffer.get_bits(if is_mono { 5 } else { 3 }).unwrap() as u8;
-        let scfsi = buffer.get_bits(if is_mono { 4 } else { 8 }).unwrap() as u8;
-
-        let granules_count: u8 = if is_mono { 2 } else { 4 };
+    /// Reads the side info following the header (and CRC). MPEG-2 and 2.5 frames carry a single
+    /// granule, a shorter `main_data_begin`, no `scfsi` and a 9 bit `scalefac_compress`.
+    pub fn create_from_buffer(buffer: &mut Buffer, header: &Header) -> Result<Self, ErrorType> {
+        let is_mono = header.get_channels() == 1;
+        let is_mpeg1 = header.version == Version::MPEG1;
+
+        let (main_data_begin, private_bits, scfsi) = if is_mpeg1 {
+            (
+                buffer.get_bits(9)? as u16,
+                buffer.get_bits(if is_mono { 5 } else { 3 })? as u8,
+                buffer.get_bits(if is_mono { 4 } else { 8 })? as u8,
+            )
+        } else {
+            (
+                buffer.get_bits(8)? as u16,
+                buffer.get_bits(if is_mono { 1 } else { 2 })? as u8,
+                0,
+            )
+        };
+
+        let granules_count = header.get_granules() * header.get_channels();
         let mut granules: Vec<GranuleInfo> = Vec::new();
         let mut part_23_sum: usize = 0;
 
         for _ in 0..granules_count {
             let mut granule = GranuleInfo::new();
 
-            granule.part_23_length = buffer.get_bits(12).unwrap() as u16;
+            granule.part_23_length = buffer.get_bits(12)? as u16;
             part_23_sum += granule.part_23_length as usize;
 
-            granule.big_values = buffer.get_bits(9).unwrap() as u16;
+            granule.big_values = buffer.get_bits(9)? as u16;
 
             if granule.big_values > 288 {
                 return Err(ErrorType::BigValuesOutOfRange);
             }
 
-            granule.global_gain = buffer.get_bits(8).unwrap() as u8;
-            granule.scalefac_compress = buffer.get_bits(4).unwrap() as u8;
-            granule.windows_switching = buffer.get_bits(1).unwrap() == 1;
+            granule.global_gain = buffer.get_bits(8)? as u8;
+            granule.scalefac_compress = buffer.get_bits(if is_mpeg1 { 4 } else { 9 })? as u16;
+            granule.windows_switching = buffer.get_bits(1)? == 1;
 
             if granule.windows_switching {
-                granule.block_type = buffer.get_bits(2).unwrap() as u8;
+                granule.block_type = buffer.get_bits(2)? as u8;
                 if granule.block_type == 0 {
                     return Err(ErrorType::BlockTypeForbidden);
                 }
 
-                granule.mixed_block_flag = buffer.get_bits(1).unwrap() == 1;
+                granule.mixed_block_flag = buffer.get_bits(1)? == 1;
 
                 for i in 0..2 {
-                    granule.table_select[i] = buffer.get_bits(5).unwrap() as u8;
+                    granule.table_select[i] = buffer.get_bits(5)? as u8;
                 }
 
                 for i in 0..3 {
-                    granule.subblock_gain[i] = buffer.get_bits(3).unwrap() as u8;
+                    granule.subblock_gain[i] = buffer.get_bits(3)? as u8;
                 }
             } else {
                 for i in 0..3 {
-                    granule.table_select[i] = buffer.get_bits(5).unwrap() as u8;
+                    granule.table_select[i] = buffer.get_bits(5)? as u8;
                 }
 
-                granule.region_count[0] = buffer.get_bits(4).unwrap() as u8;
-                granule.region_count[1] = buffer.get_bits(3).unwrap() as u8;
+                granule.region_count[0] = buffer.get_bits(4)? as u8;
+                granule.region_count[1] = buffer.get_bits(3)? as u8;
                 granule.region_count[2] = 255;
             }
 
-            let bits = buffer.get_bits(3).unwrap() as u8;
-            granule.preflag = bits & 4 == 4;
-            granule.scalefac_scale = bits & 2 == 2;
-            granule.count1_table_select = bits & 1 == 1;
+            // MPEG-2 derives preflag from scalefac_compress while reading the scalefactors.
+            if is_mpeg1 {
+                granule.preflag = buffer.get_bits(1)? == 1;
+            }
+
+            granule.scalefac_scale = buffer.get_bits(1)? == 1;
+            granule.count1_table_select = buffer.get_bits(1)? == 1;
 
             granules.push(granule);
         }
@@ -118,3 +144,75 @@ impl SideInfo {
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Side info fields of a long block granule with the given part2_3_length and
+    /// scalefac_compress, as MPEG-2 lays them out.
+    fn lsf_granule(part_23_length: u32, scalefac_compress: u32) -> Vec<(u32, u32)> {
+        vec![
+            (part_23_length, 12),
+            (20, 9),
+            (150, 8),
+            (scalefac_compress, 9),
+            (0, 1),
+            (1, 5),
+            (2, 5),
+            (3, 5),
+            (4, 4),
+            (5, 3),
+            (1, 1),
+            (0, 1),
+        ]
+    }
+
+    #[test]
+    fn test_lsf_side_info() {
+        // MPEG-2, 64 kbit/s, 22.05 kHz, stereo.
+        let mut fields = vec![(0xab, 8), (0b10, 2)];
+        fields.extend(lsf_granule(100, 499));
+        fields.extend(lsf_granule(200, 500));
+
+        let mut buffer = Buffer::create_buffer_from_fields(&[0xff, 0xf3, 0x80, 0x00], &fields, 208);
+        let header = Header::create_from_buffer(&mut buffer).unwrap();
+        let side_info = SideInfo::create_from_buffer(&mut buffer, &header).unwrap();
+
+        // 8 bit main_data_begin, 2 private bits, no scfsi and a single granule of 63 bits.
+        assert_eq!(buffer.pos, (4 + 17) * 8);
+        assert_eq!(side_info.main_data_begin, 0xab);
+        assert_eq!(side_info.private_bits, 0b10);
+        assert_eq!(side_info.scfsi, 0);
+        assert_eq!(side_info.granule_channels.len(), 2);
+
+        let granule = &side_info.granule_channels[1];
+        assert_eq!(granule.part_23_length, 200);
+        assert_eq!(granule.big_values, 20);
+        assert_eq!(granule.global_gain, 150);
+        assert_eq!(granule.scalefac_compress, 500);
+        assert_eq!(granule.table_select, [1, 2, 3]);
+        assert_eq!(granule.region_count[..2], [4, 5]);
+        // Set while reading the scalefactors, not by a side info bit.
+        assert!(!granule.preflag);
+        assert!(granule.scalefac_scale);
+        assert!(!granule.count1_table_select);
+    }
+
+    #[test]
+    fn test_lsf_side_info_mono() {
+        // MPEG-2.5, 32 kbit/s, 8 kHz, mono: a single private bit.
+        let mut fields = vec![(0x12, 8), (1, 1)];
+        fields.extend(lsf_granule(100, 0x1ff));
+
+        let mut buffer = Buffer::create_buffer_from_fields(&[0xff, 0xe3, 0x48, 0xc0], &fields, 288);
+        let header = Header::create_from_buffer(&mut buffer).unwrap();
+        let side_info = SideInfo::create_from_buffer(&mut buffer, &header).unwrap();
+
+        assert_eq!(buffer.pos, (4 + 9) * 8);
+        assert_eq!(side_info.main_data_begin, 0x12);
+        assert_eq!(side_info.private_bits, 1);
+        assert_eq!(side_info.granule_channels.len(), 1);
+        assert_eq!(side_info.granule_channels[0].scalefac_compress, 0x1ff);
+    }
+}
@@ -0,0 +1,341 @@
+use std::f32::consts::{FRAC_1_SQRT_2, PI};
+
+use crate::{
+    constant,
+    header::{Header, Mode, Version},
+    side_info::GranuleInfo,
+};
+
+const ILLEGAL_INTENSITY_POSITION: u8 = 7;
+
+fn intensity_gains(position: u8) -> (f32, f32) {
+    let angle = position as f32 * PI / 12.0;
+    let (sin, cos) = angle.sin_cos();
+
+    (sin / (sin + cos), cos / (sin + cos))
+}
+
+/// MPEG-2 intensity gains: one channel keeps the value while the other is attenuated by
+/// `2^(-1/4)` (or `2^(-1/2)` with `intensity_scale`) per step of the position.
+fn intensity_gains_lsf(position: u8, intensity_scale: bool) -> (f32, f32) {
+    let step: f32 = if intensity_scale { -0.5 } else { -0.25 };
+
+    if position == 0 {
+        (1.0, 1.0)
+    } else if position % 2 == 1 {
+        ((step * (position as f32 + 1.0) / 2.0).exp2(), 1.0)
+    } else {
+        (1.0, (step * position as f32 / 2.0).exp2())
+    }
+}
+
+fn is_zero(spectrum: &[f32]) -> bool {
+    spectrum.iter().all(|&line| line == 0.0)
+}
+
+/// Intensity position and illegal position of the scalefactor at `index`.
+fn intensity_position(scale_factor: &[u8], illegal_positions: &[u8], index: usize) -> (u8, u8) {
+    (
+        scale_factor.get(index).copied().unwrap_or(0),
+        illegal_positions
+            .get(index)
+            .copied()
+            .unwrap_or(ILLEGAL_INTENSITY_POSITION),
+    )
+}
+
+/// Marks the lines of a band with its intensity position, unless the position is illegal in
+/// which case the band falls back to the M/S (or plain L/R) path.
+fn mark_band(
+    positions: &mut [Option<u8>; 576],
+    lines: std::ops::Range<usize>,
+    (position, illegal_position): (u8, u8),
+) {
+    if position == illegal_position {
+        return;
+    }
+
+    for line in lines {
+        positions[line] = Some(position);
+    }
+}
+
+fn intensity_positions_long(
+    scale_factor: &[u8],
+    illegal_positions: &[u8],
+    right: &[f32; 576],
+    band_long: &[u16; 23],
+    bands: std::ops::Range<usize>,
+    positions: &mut [Option<u8>; 576],
+) {
+    let first_band = bands
+        .clone()
+        .rev()
+        .find(|&band_index| {
+            !is_zero(&right[band_long[band_index] as usize..band_long[band_index + 1] as usize])
+        })
+        .map_or(bands.start, |band_index| band_index + 1);
+
+    for band_index in first_band..bands.end {
+        // The last long band has no scalefactor of its own and reuses the one of band 20.
+        let position = intensity_position(scale_factor, illegal_positions, band_index.min(20));
+        let lines = band_long[band_index] as usize..band_long[band_index + 1] as usize;
+
+        mark_band(positions, lines, position);
+    }
+}
+
+/// Returns whether any of the short windows has non-zero lines in the right channel.
+fn intensity_positions_short(
+    scale_factor: &[u8],
+    illegal_positions: &[u8],
+    right: &[f32; 576],
+    band_short: &[u16; 14],
+    (first_band, long_bands): (usize, usize),
+    positions: &mut [Option<u8>; 576],
+) -> bool {
+    let offset = if first_band > 0 { long_bands } else { 0 };
+    let mut has_non_zero = false;
+
+    for window in 0..3 {
+        let window_lines = |band_index: usize| {
+            let width = (band_short[band_index + 1] - band_short[band_index]) as usize;
+            let start = band_short[band_index] as usize * 3 + window * width;
+
+            start..start + width
+        };
+
+        let start_band = (first_band..13)
+            .rev()
+            .find(|&band_index| !is_zero(&right[window_lines(band_index)]))
+            .map_or(first_band, |band_index| {
+                has_non_zero = true;
+                band_index + 1
+            });
+
+        for band_index in start_band..13 {
+            // The last short band reuses the scalefactor of band 11.
+            let index = offset + (band_index.min(11) - first_band) * 3 + window;
+            let position = intensity_position(scale_factor, illegal_positions, index);
+
+            mark_band(positions, window_lines(band_index), position);
+        }
+    }
+
+    has_non_zero
+}
+
+/// Reconstructs left/right spectra of a jointly coded granule. `granule`, `scale_factor` and
+/// `illegal_positions` belong to the right channel, which carries the intensity positions. An
+/// empty `illegal_positions` means the MPEG-1 illegal position 7 for every band.
+pub fn process_stereo(
+    header: &Header,
+    granule: &GranuleInfo,
+    scale_factor: &[u8],
+    illegal_positions: &[u8],
+    left: &mut [f32; 576],
+    right: &mut [f32; 576],
+) {
+    if header.mode != Mode::JointStereo {
+        return;
+    }
+
+    let mut positions: [Option<u8>; 576] = [None; 576];
+
+    if header.intensity_stereo {
+        let sample_rate_index = header.get_sample_rate_index();
+        let band_long = &constant::SCALEFACTOR_BAND_LONG[sample_rate_index];
+        let band_short = &constant::SCALEFACTOR_BAND_SHORT[sample_rate_index];
+        let long_bands = constant::mixed_block_long_bands(sample_rate_index);
+
+        if granule.windows_switching && granule.block_type == 2 {
+            if granule.mixed_block_flag {
+                let has_non_zero = intensity_positions_short(
+                    scale_factor,
+                    illegal_positions,
+                    right,
+                    band_short,
+                    (3, long_bands),
+                    &mut positions,
+                );
+
+                // The long part only carries intensity data when all short windows are zero.
+                if !has_non_zero {
+                    intensity_positions_long(
+                        scale_factor,
+                        illegal_positions,
+                        right,
+                        band_long,
+                        0..long_bands,
+                        &mut positions,
+                    );
+                }
+            } else {
+                intensity_positions_short(
+                    scale_factor,
+                    illegal_positions,
+                    right,
+                    band_short,
+                    (0, long_bands),
+                    &mut positions,
+                );
+            }
+        } else {
+            intensity_positions_long(
+                scale_factor,
+                illegal_positions,
+                right,
+                band_long,
+                0..22,
+                &mut positions,
+            );
+        }
+    }
+
+    // For MPEG-2 the lowest bit of the right channel's scalefac_compress is intensity_scale.
+    let intensity_scale = granule.scalefac_compress & 1 == 1;
+
+    for (line, position) in positions.iter().enumerate() {
+        match position {
+            Some(position) => {
+                let (left_gain, right_gain) = if header.version == Version::MPEG1 {
+                    intensity_gains(*position)
+                } else {
+                    intensity_gains_lsf(*position, intensity_scale)
+                };
+                let value = left[line];
+
+                left[line] = value * left_gain;
+                right[line] = value * right_gain;
+            }
+            None if header.ms_stereo => {
+                let middle = left[line];
+                let side = right[line];
+
+                left[line] = (middle + side) * FRAC_1_SQRT_2;
+                right[line] = (middle - side) * FRAC_1_SQRT_2;
+            }
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::header::{Layer, Version};
+
+    use super::*;
+
+    fn joint_stereo_header(intensity_stereo: bool, ms_stereo: bool) -> Header {
+        Header {
+            sync_word: 0xfff,
+            version: Version::MPEG1,
+            layer: Layer::Layer3,
+            error_protection: false,
+            bitrate: 0b1001,
+            frequency: 0,
+            padding_bit: false,
+            private_bit: false,
+            mode: Mode::JointStereo,
+            intensity_stereo,
+            ms_stereo,
+            copy_right: false,
+            copy_of_original: false,
+            emphasis: 0,
+            pos: 0,
+        }
+    }
+
+    #[test]
+    fn test_ms_stereo() {
+        let header = joint_stereo_header(false, true);
+        let granule = GranuleInfo::new();
+
+        let mut left = [0.0; 576];
+        let mut right = [0.0; 576];
+        left[0] = 1.0;
+        right[0] = 1.0;
+        left[575] = 2.0;
+
+        process_stereo(&header, &granule, &[0; 22], &[], &mut left, &mut right);
+
+        assert!((left[0] - 2.0 * FRAC_1_SQRT_2).abs() < 1e-6);
+        assert_eq!(right[0], 0.0);
+        assert!((left[575] - 2.0 * FRAC_1_SQRT_2).abs() < 1e-6);
+        assert!((right[575] - 2.0 * FRAC_1_SQRT_2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_intensity_stereo_long_block() {
+        let header = joint_stereo_header(true, true);
+        let granule = GranuleInfo::new();
+
+        let mut scale_factor = [3; 22];
+        // Band 19 (lines 288..342) uses the illegal position and falls back to M/S.
+        scale_factor[19] = ILLEGAL_INTENSITY_POSITION;
+        scale_factor[20] = 6;
+
+        let mut left = [1.0; 576];
+        let mut right = [0.0; 576];
+        right[10] = 1.0;
+
+        process_stereo(&header, &granule, &scale_factor, &[], &mut left, &mut right);
+
+        // Band 2 holds the last non-zero right line, so it is still M/S coded.
+        assert!((left[10] - 2.0 * FRAC_1_SQRT_2).abs() < 1e-6);
+        assert!((left[12] - 0.5).abs() < 1e-6);
+        assert!((right[12] - 0.5).abs() < 1e-6);
+        assert!((left[300] - FRAC_1_SQRT_2).abs() < 1e-6);
+        assert!((right[300] - FRAC_1_SQRT_2).abs() < 1e-6);
+        assert!((left[500] - 1.0).abs() < 1e-6);
+        assert!(right[500].abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_intensity_stereo_lsf_illegal_positions() {
+        let mut header = joint_stereo_header(true, false);
+        header.version = Version::MPEG2;
+
+        let granule = GranuleInfo::new();
+        let mut scale_factor = vec![1; 22];
+        let mut illegal_positions = vec![3; 21];
+
+        // Band 4 (lines 24..30) uses the largest value of its slen, band 10 (lines 80..96) was
+        // read with no bits at all.
+        scale_factor[4] = 3;
+        scale_factor[10] = 0;
+        illegal_positions[10] = u8::MAX;
+
+        let mut left = [1.0; 576];
+        let mut right = [0.0; 576];
+
+        process_stereo(
+            &header,
+            &granule,
+            &scale_factor,
+            &illegal_positions,
+            &mut left,
+            &mut right,
+        );
+
+        assert!((left[0] - (-0.25f32).exp2()).abs() < 1e-6);
+        assert_eq!(right[0], 1.0);
+        assert_eq!((left[24], right[24]), (1.0, 0.0));
+        assert_eq!((left[80], right[80]), (1.0, 1.0));
+        // The last band takes the position of band 20.
+        assert!((left[575] - (-0.25f32).exp2()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_intensity_gains_lsf() {
+        assert_eq!(intensity_gains_lsf(0, false), (1.0, 1.0));
+
+        let (left, right) = intensity_gains_lsf(1, false);
+        assert!((left - (-0.25f32).exp2()).abs() < 1e-6);
+        assert_eq!(right, 1.0);
+
+        let (left, right) = intensity_gains_lsf(4, true);
+        assert_eq!(left, 1.0);
+        assert!((right - 0.5).abs() < 1e-6);
+    }
+}
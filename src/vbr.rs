@@ -0,0 +1,458 @@
+use crate::{
+    buffer::Buffer,
+    crc,
+    header::{Header, Layer},
+};
+
+const FLAG_FRAMES: u32 = 0x1;
+const FLAG_BYTES: u32 = 0x2;
+const FLAG_TOC: u32 = 0x4;
+const FLAG_QUALITY: u32 = 0x8;
+
+/// The VBRI header always starts 32 bytes after the frame header, whatever the side info length.
+const VBRI_OFFSET: usize = 4 + 32;
+
+/// Tag found in the first frame of the stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VbrTag {
+    /// `Xing`, written for variable bitrate streams.
+    Xing,
+    /// `Info`, the same layout written by LAME for constant bitrate streams.
+    Info,
+    /// `VBRI`, written by the Fraunhofer encoder.
+    Vbri,
+}
+
+/// Fields only found in a Fraunhofer VBRI header.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vbri {
+    pub version: u16,
+    /// Encoder delay in samples.
+    pub delay: u16,
+    /// Factor the stored seek table entries were divided by.
+    pub scale: u16,
+    /// Width of a stored seek table entry in bytes, 1 to 4.
+    pub entry_bytes: u16,
+    pub frames_per_entry: u16,
+    /// Length in bytes of each run of `frames_per_entry` frames, already multiplied by `scale`.
+    pub seek_table: Vec<u64>,
+}
+
+/// Length of the LAME extension that follows the Xing/Info fields.
+const LAME_TAG_LENGTH: usize = 36;
+
+/// LAME extension of the Xing/Info tag.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LameTag {
+    /// Encoder version string, such as `LAME3.100`.
+    pub encoder: String,
+    pub revision: u8,
+    /// 1 and 8 for CBR, 2 and 9 for ABR, 3 to 6 for the VBR modes.
+    pub vbr_method: u8,
+    /// Lowpass filter frequency in Hz.
+    pub lowpass: u32,
+    /// Peak signal amplitude, 1.0 being full scale. 0.0 when the encoder did not compute it.
+    pub peak_amplitude: f32,
+    /// Radio (track) ReplayGain adjustment in dB.
+    pub radio_replay_gain: Option<f32>,
+    /// Audiophile (album) ReplayGain adjustment in dB.
+    pub audiophile_replay_gain: Option<f32>,
+    /// Samples the encoder added in front of the audio.
+    pub encoder_delay: u16,
+    /// Samples the encoder appended to fill the last frame.
+    pub end_padding: u16,
+    /// Length of the stream in bytes, starting with the frame holding the tag.
+    pub music_length: u32,
+    /// CRC-16/ARC of the audio frames.
+    pub music_crc: u16,
+    /// Whether the tag CRC, over the first 190 bytes of the frame, matched.
+    pub crc_valid: bool,
+}
+
+/// A ReplayGain field: 3 bits name (0 when unset), 3 bits originator, a sign bit and the
+/// adjustment in 0.1 dB.
+fn replay_gain(field: u16) -> Option<f32> {
+    if field >> 13 == 0 {
+        return None;
+    }
+
+    let gain = (field & 0x1ff) as f32 / 10.0;
+
+    Some(if field & 0x200 != 0 { -gain } else { gain })
+}
+
+impl LameTag {
+    /// Reads the tag at `offset` of the bytes starting with its frame.
+    fn create_from_bytes(frame: &[u8], offset: usize) -> Option<Self> {
+        let data = frame.get(offset..offset + LAME_TAG_LENGTH)?;
+
+        if !data.starts_with(b"LAME") {
+            return None;
+        }
+
+        let delay_padding = read_uint(data, 21, 3)?;
+        let tag_crc = read_u16(data, 34)?;
+
+        Some(Self {
+            encoder: String::from_utf8_lossy(&data[..9])
+                .trim_end_matches(['\0', ' '])
+                .to_string(),
+            revision: data[9] >> 4,
+            vbr_method: data[9] & 0xf,
+            lowpass: data[10] as u32 * 100,
+            // 9.23 fixed point.
+            peak_amplitude: read_u32(data, 11)? as f32 / (1u32 << 23) as f32,
+            radio_replay_gain: replay_gain(read_u16(data, 15)?),
+            audiophile_replay_gain: replay_gain(read_u16(data, 17)?),
+            encoder_delay: (delay_padding >> 12) as u16,
+            end_padding: (delay_padding & 0xfff) as u16,
+            music_length: read_u32(data, 28)?,
+            music_crc: read_u16(data, 32)?,
+            crc_valid: crc::crc16_arc(&frame[..offset + LAME_TAG_LENGTH - 2]) == tag_crc,
+        })
+    }
+}
+
+/// Stream information from the Xing/Info or VBRI tag that encoders put in an otherwise silent
+/// first frame, so the duration and seek positions of a VBR file are known without scanning it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VbrInfo {
+    pub tag: VbrTag,
+    /// Number of audio frames, not counting the frame holding the tag.
+    pub frames: Option<u32>,
+    /// Length of the stream in bytes, starting with the frame holding the tag.
+    pub bytes: Option<u32>,
+    /// Xing seek table: entry `i` is the byte position at `i` percent of the duration, in 1/256
+    /// of `bytes`.
+    pub toc: Option<[u8; 100]>,
+    /// Encoder quality indicator, from 0 (best) to 100.
+    pub quality: Option<u32>,
+    pub vbri: Option<Vbri>,
+    pub lame: Option<LameTag>,
+    pub sample_rate: u32,
+    /// Samples per channel in each frame.
+    pub frame_samples: usize,
+}
+
+/// Big endian unsigned integer of `length` (up to 4) bytes at `offset`.
+fn read_uint(data: &[u8], offset: usize, length: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + length)?;
+
+    Some(
+        bytes
+            .iter()
+            .fold(0, |value, &byte| value << 8 | byte as u32),
+    )
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    read_uint(data, offset, 4)
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(read_uint(data, offset, 2)? as u16)
+}
+
+impl VbrInfo {
+    /// Reads the Xing/Info or VBRI tag of the Layer III frame whose `header` was read from
+    /// `buffer`.
+    pub fn create_from_buffer(buffer: &Buffer, header: &Header) -> Option<Self> {
+        if header.layer != Layer::Layer3 {
+            return None;
+        }
+
+        Self::read_xing(buffer, header).or_else(|| Self::read_vbri(buffer, header))
+    }
+
+    /// The Xing/Info tag sits where the main data of the frame would start, after the side info.
+    fn read_xing(buffer: &Buffer, header: &Header) -> Option<Self> {
+        let crc_length = if header.error_protection { 2 } else { 0 };
+        let start = 4 + crc_length + header.get_side_info_length();
+        let frame = buffer.data.get(header.pos / 8..)?;
+        let data = frame.get(start..)?;
+
+        let tag = match data.get(..4)? {
+            b"Xing" => VbrTag::Xing,
+            b"Info" => VbrTag::Info,
+            _ => return None,
+        };
+
+        let flags = read_u32(data, 4)?;
+        let mut offset = 8;
+
+        let mut read_field = |flag: u32, length: usize| -> Option<Option<&[u8]>> {
+            if flags & flag == 0 {
+                return Some(None);
+            }
+
+            let field = data.get(offset..offset + length)?;
+            offset += length;
+
+            Some(Some(field))
+        };
+
+        let frames = read_field(FLAG_FRAMES, 4)?.and_then(|field| read_u32(field, 0));
+        let bytes = read_field(FLAG_BYTES, 4)?.and_then(|field| read_u32(field, 0));
+        let toc = read_field(FLAG_TOC, 100)?.map(|field| {
+            let mut toc = [0u8; 100];
+            toc.copy_from_slice(field);
+            toc
+        });
+        let quality = read_field(FLAG_QUALITY, 4)?.and_then(|field| read_u32(field, 0));
+        let lame = LameTag::create_from_bytes(frame, start + offset);
+
+        Some(Self {
+            tag,
+            frames,
+            bytes,
+            toc,
+            quality,
+            vbri: None,
+            lame,
+            sample_rate: header.get_frequency().ok()? as u32,
+            frame_samples: header.get_frame_samples(),
+        })
+    }
+
+    fn read_vbri(buffer: &Buffer, header: &Header) -> Option<Self> {
+        let data = buffer.data.get(header.pos / 8 + VBRI_OFFSET..)?;
+
+        if data.get(..4)? != b"VBRI" {
+            return None;
+        }
+
+        let entries = read_u16(data, 18)? as usize;
+        let scale = read_u16(data, 20)?;
+        let entry_bytes = read_u16(data, 22)?;
+
+        if !(1..=4).contains(&entry_bytes) {
+            return None;
+        }
+
+        let seek_table = (0..entries)
+            .map(|index| {
+                let entry = read_uint(
+                    data,
+                    26 + index * entry_bytes as usize,
+                    entry_bytes as usize,
+                )?;
+                // Four byte entries scaled by up to 65535 do not fit in 32 bits.
+                Some(entry as u64 * scale as u64)
+            })
+            .collect::<Option<Vec<u64>>>()?;
+
+        Some(Self {
+            tag: VbrTag::Vbri,
+            frames: Some(read_u32(data, 14)?),
+            bytes: Some(read_u32(data, 10)?),
+            toc: None,
+            quality: Some(read_u16(data, 8)? as u32),
+            vbri: Some(Vbri {
+                version: read_u16(data, 4)?,
+                delay: read_u16(data, 6)?,
+                scale,
+                entry_bytes,
+                frames_per_entry: read_u16(data, 24)?,
+                seek_table,
+            }),
+            lame: None,
+            sample_rate: header.get_frequency().ok()? as u32,
+            frame_samples: header.get_frame_samples(),
+        })
+    }
+
+    /// Samples the encoder added in front of the audio, from the LAME tag or the VBRI header.
+    pub fn encoder_delay(&self) -> Option<u16> {
+        self.lame
+            .as_ref()
+            .map(|lame| lame.encoder_delay)
+            .or_else(|| self.vbri.as_ref().map(|vbri| vbri.delay))
+    }
+
+    /// Samples the encoder appended to fill the last frame, from the LAME tag.
+    pub fn end_padding(&self) -> Option<u16> {
+        self.lame.as_ref().map(|lame| lame.end_padding)
+    }
+
+    /// Number of samples per channel in the stream.
+    pub fn samples(&self) -> Option<u64> {
+        Some(self.frames? as u64 * self.frame_samples as u64)
+    }
+
+    /// Duration of the stream in seconds.
+    pub fn duration(&self) -> Option<f64> {
+        Some(self.samples()? as f64 / self.sample_rate as f64)
+    }
+
+    /// Byte offset from the start of the tag frame at `percent` (0.0 to 100.0) of the
+    /// duration, interpolated between the entries of the Xing TOC or the VBRI seek table.
+    pub fn seek_position(&self, percent: f64) -> Option<u64> {
+        let percent = percent.clamp(0.0, 100.0);
+
+        if let Some(vbri) = &self.vbri {
+            return Some(vbri.seek_position(percent / 100.0 * self.frames? as f64));
+        }
+
+        let toc = self.toc?;
+        let bytes = self.bytes? as f64;
+        let index = (percent as usize).min(99);
+
+        let low = toc[index] as f64;
+        let high = toc.get(index + 1).map_or(256.0, |&entry| entry as f64);
+        let position = low + (high - low) * (percent - index as f64);
+
+        Some((position / 256.0 * bytes) as u64)
+    }
+}
+
+impl Vbri {
+    /// Sums the lengths of the runs before `frame` and interpolates within the run holding it.
+    fn seek_position(&self, frame: f64) -> u64 {
+        let frames_per_entry = self.frames_per_entry as f64;
+        let mut position = 0.0;
+        let mut start = 0.0;
+
+        for &length in &self.seek_table {
+            if frame < start + frames_per_entry {
+                position += length as f64 * (frame - start) / frames_per_entry;
+                break;
+            }
+
+            position += length as f64;
+            start += frames_per_entry;
+        }
+
+        position as u64
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_xing_tag() {
+        let mut buffer = Buffer::create_buffer_from_file("mp3-examples/test_data_1mb.mp3").unwrap();
+        let header = Header::create_from_buffer(&mut buffer).unwrap();
+        let info = VbrInfo::create_from_buffer(&buffer, &header).unwrap();
+
+        assert_eq!(info.tag, VbrTag::Xing);
+        assert_eq!(info.frames, Some(1649));
+        assert_eq!(info.bytes, Some(1051422));
+        assert_eq!(info.toc.unwrap()[..4], [0, 3, 6, 8]);
+        assert_eq!(info.samples(), Some(1649 * 1152));
+        assert!((info.duration().unwrap() - 43.075).abs() < 1e-3);
+
+        assert_eq!(info.seek_position(0.0), Some(0));
+        assert_eq!(info.seek_position(1.0), Some(3 * 1051422 / 256));
+        assert_eq!(info.seek_position(100.0), Some(1051422));
+    }
+
+    #[test]
+    fn test_lame_tag() {
+        let mut buffer =
+            Buffer::create_buffer_from_file("mp3-examples/test_data_100kb.mp3").unwrap();
+        let header = Header::create_from_buffer(&mut buffer).unwrap();
+        let info = VbrInfo::create_from_buffer(&buffer, &header).unwrap();
+        assert_eq!(info.encoder_delay(), Some(576));
+        assert_eq!(info.end_padding(), Some(679));
+
+        let lame = info.lame.unwrap();
+
+        assert_eq!(lame.encoder, "LAME3.100");
+        assert_eq!(lame.vbr_method, 4);
+        assert_eq!(lame.lowpass, 18500);
+        assert_eq!(lame.peak_amplitude, 0.0);
+        assert_eq!(lame.radio_replay_gain, None);
+        assert_eq!(lame.encoder_delay, 576);
+        assert_eq!(lame.end_padding, 679);
+        assert_eq!(lame.music_length, 103016);
+        assert!(lame.crc_valid);
+
+        buffer.data.to_mut()[160] ^= 1;
+        let info = VbrInfo::create_from_buffer(&buffer, &header).unwrap();
+        assert!(!info.lame.unwrap().crc_valid);
+
+        // A peak of half the full scale.
+        buffer.data.to_mut()[167..171].copy_from_slice(&[0, 0x40, 0, 0]);
+        let info = VbrInfo::create_from_buffer(&buffer, &header).unwrap();
+        assert_eq!(info.lame.unwrap().peak_amplitude, 0.5);
+    }
+
+    #[test]
+    fn test_replay_gain() {
+        assert_eq!(replay_gain(0), None);
+        // Radio gain set by the user, -6.4 dB.
+        assert_eq!(replay_gain(0x2a40), Some(-6.4));
+        // Audiophile gain determined automatically, +1.5 dB.
+        assert_eq!(replay_gain(0x4c0f), Some(1.5));
+    }
+
+    #[test]
+    fn test_frame_without_tag() {
+        let mut buffer = Buffer::create_buffer_from_file("mp3-examples/test_data_1mb.mp3").unwrap();
+        buffer.set_pos(417 * 8);
+        let header = Header::create_from_buffer(&mut buffer).unwrap();
+
+        assert_eq!(VbrInfo::create_from_buffer(&buffer, &header), None);
+    }
+
+    #[test]
+    fn test_vbri_tag() {
+        let mut buffer =
+            Buffer::create_buffer_from_file("mp3-examples/test_data_100kb.mp3").unwrap();
+
+        let mut vbri = b"VBRI".to_vec();
+        // Version 1, delay 576, quality 75, 103016 bytes, 153 frames.
+        vbri.extend([0, 1, 0x02, 0x40, 0, 75, 0, 1, 0x92, 0x68, 0, 0, 0, 153]);
+        // Three entries of 2 bytes, scaled by 2, for 50 frames each.
+        vbri.extend([0, 3, 0, 2, 0, 2, 0, 50]);
+        vbri.extend([0, 100, 0, 200, 0, 150]);
+        buffer.data.to_mut().splice(36..36 + vbri.len(), vbri);
+
+        let header = Header::create_from_buffer(&mut buffer).unwrap();
+        let info = VbrInfo::create_from_buffer(&buffer, &header).unwrap();
+        let vbri = info.vbri.as_ref().unwrap();
+
+        assert_eq!(info.tag, VbrTag::Vbri);
+        assert_eq!(info.frames, Some(153));
+        assert_eq!(info.bytes, Some(103016));
+        assert_eq!(info.quality, Some(75));
+        assert_eq!(info.toc, None);
+        assert_eq!(vbri.version, 1);
+        assert_eq!(vbri.delay, 576);
+        assert_eq!(info.encoder_delay(), Some(576));
+        assert_eq!(info.end_padding(), None);
+        assert_eq!(vbri.frames_per_entry, 50);
+        assert_eq!(vbri.seek_table, [200, 400, 300]);
+
+        assert_eq!(info.seek_position(0.0), Some(0));
+        // 51 of 153 frames: all of the first run and 1/50 of the second one.
+        assert_eq!(info.seek_position(100.0 / 3.0), Some(208));
+        assert_eq!(info.seek_position(100.0), Some(900));
+    }
+
+    #[test]
+    fn test_vbri_tag_with_large_entries() {
+        let mut buffer =
+            Buffer::create_buffer_from_file("mp3-examples/test_data_100kb.mp3").unwrap();
+
+        let mut vbri = b"VBRI".to_vec();
+        vbri.extend([0, 1, 0x02, 0x40, 0, 75, 0, 1, 0x92, 0x68, 0, 0, 0, 153]);
+        // One entry of 4 bytes, scaled by 2.
+        vbri.extend([0, 1, 0, 2, 0, 4, 0, 153]);
+        vbri.extend([0x70, 0, 0, 0]);
+        buffer.data.to_mut().splice(36..36 + vbri.len(), vbri);
+
+        let header = Header::create_from_buffer(&mut buffer).unwrap();
+        let info = VbrInfo::create_from_buffer(&buffer, &header).unwrap();
+        assert_eq!(info.vbri.unwrap().seek_table, [0xe000_0000]);
+
+        // Scaled beyond 32 bits.
+        buffer.data.to_mut()[62] = 0x90;
+        let info = VbrInfo::create_from_buffer(&buffer, &header).unwrap();
+        assert_eq!(info.frames, Some(153));
+        assert_eq!(info.encoder_delay(), Some(576));
+        assert_eq!(info.vbri.unwrap().seek_table, [0x1_2000_0000]);
+    }
+}